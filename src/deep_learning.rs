@@ -1,6 +1,20 @@
+pub mod callback;
+pub mod checkpoint;
 pub mod common;
+pub mod data_parallel;
+pub mod cross_validation;
+pub mod early_stopping;
+pub mod ensemble;
+pub mod gradient_check;
 pub mod graph_plotter;
+pub mod history;
+pub mod hyperparameter_search;
 pub mod layer;
+pub mod learning_rate_finder;
+pub mod metrics;
+pub mod metrics_logger;
 pub mod neural_network;
 pub mod optimizer;
-pub mod statistics;
\ No newline at end of file
+pub mod scheduler;
+pub mod statistics;
+pub mod summary;
\ No newline at end of file
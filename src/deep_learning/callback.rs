@@ -0,0 +1,148 @@
+use crate::deep_learning::checkpoint::*;
+use crate::deep_learning::graph_plotter::*;
+use crate::deep_learning::neural_network::*;
+
+pub struct IterationMetrics {
+    pub iteration: u32,
+    // Loss of the learned minibatch
+    pub train_loss: f64,
+    // Test results after the iteration
+    pub loss: f64,
+    pub correct_rate: f64,
+    // Set when a scheduler is used
+    pub learning_rate_factor: Option<f64>,
+    // Norm before clipping. Set when gradient clipping is used
+    pub gradient_norm: Option<f64>,
+}
+
+pub struct EvaluationMetrics {
+    pub iteration: u32,
+    pub val_loss: f64,
+    pub val_correct_rate: f64,
+}
+
+// Hooks called by NeuralNetwork::learn.
+// on_iteration_end and on_evaluation return true to stop learning.
+pub trait TrainingCallback {
+    fn on_train_begin(&mut self, _network: &mut NeuralNetwork, _checkpoint: &Checkpoint) {}
+    fn on_iteration_end(&mut self, _network: &mut NeuralNetwork, _metrics: &IterationMetrics) -> bool {
+        false
+    }
+    fn on_evaluation(&mut self, _network: &mut NeuralNetwork, _metrics: &EvaluationMetrics) -> bool {
+        false
+    }
+    fn on_train_end(&mut self, _network: &mut NeuralNetwork, _checkpoint: &Checkpoint) {}
+}
+
+// Plot test correct rate, loss and parameter histograms at the end of learning
+pub struct PlotCallback {}
+impl PlotCallback {
+    pub fn new() -> Self {
+        PlotCallback {}
+    }
+}
+impl TrainingCallback for PlotCallback {
+    fn on_train_end(&mut self, network: &mut NeuralNetwork, checkpoint: &Checkpoint) {
+        plot_rate(checkpoint.correct_rates.clone(), "correct_rate");
+        plot_loss(checkpoint.losses.clone(), "loss");
+        network.plot();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use ndarray::prelude::{
+        Array2,
+    };
+
+    use crate::deep_learning::layer::*;
+    use crate::deep_learning::optimizer::*;
+
+    // Record hook calls and stop at stop_iteration
+    struct RecordCallback {
+        calls: Rc<RefCell<Vec<String>>>,
+        stop_iteration: u32,
+    }
+    impl TrainingCallback for RecordCallback {
+        fn on_train_begin(&mut self, _network: &mut NeuralNetwork, checkpoint: &Checkpoint) {
+            self.calls.borrow_mut().push(format!("begin {}", checkpoint.iteration));
+        }
+        fn on_iteration_end(&mut self, _network: &mut NeuralNetwork, metrics: &IterationMetrics) -> bool {
+            self.calls.borrow_mut().push(format!("iteration {}", metrics.iteration));
+            metrics.iteration == self.stop_iteration
+        }
+        fn on_evaluation(&mut self, _network: &mut NeuralNetwork, metrics: &EvaluationMetrics) -> bool {
+            self.calls.borrow_mut().push(format!("evaluation {}", metrics.iteration));
+            false
+        }
+        fn on_train_end(&mut self, _network: &mut NeuralNetwork, checkpoint: &Checkpoint) {
+            self.calls.borrow_mut().push(format!("end {}", checkpoint.iteration));
+        }
+    }
+
+    #[test]
+    fn test_learn_callbacks() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            2,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let calls = Rc::new(RefCell::new(Vec::<String>::new()));
+        let history = nn.learn(
+            LearningParameter {
+                batch_size: batch_size,
+                iterations_num: 10,
+                sampling: MinibatchSampling::WithReplacement,
+                gradient_clipping: None,
+                scheduler: None,
+                seed: Some(0),
+                checkpoint: None,
+                validation: Some(ValidationParameter {
+                    interval: 2,
+                    early_stopping: None,
+                    restore_best_weights: false,
+                }),
+                callbacks: vec![Box::new(RecordCallback {
+                    calls: calls.clone(),
+                    stop_iteration: 2,
+                })],
+                threads_num: 1,
+            },
+            LearningResource {
+                trn_data: data.clone(),
+                trn_lbl_onehot: lbl_onehot.clone(),
+                tst_data: data.clone(),
+                tst_lbl_onehot: lbl_onehot.clone(),
+                val_data: data.clone(),
+                val_lbl_onehot: lbl_onehot.clone(),
+            }
+        );
+
+        assert_eq!(*calls.borrow(), vec![
+            "begin 0",
+            "iteration 0",
+            "iteration 1",
+            "evaluation 1",
+            "iteration 2",
+            "end 3",
+        ]);
+        assert_eq!(history.iterations.iter().map(|r| r.iteration).collect::<Vec<u32>>(), vec![0, 1, 2]);
+        assert_eq!(history.evaluations.iter().map(|r| r.iteration).collect::<Vec<u32>>(), vec![1]);
+        assert_eq!(history.iterations[0].learning_rates, vec![0.1, 0.1]);
+    }
+}
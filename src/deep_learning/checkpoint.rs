@@ -0,0 +1,116 @@
+use std::io::Write;
+
+use crate::deep_learning::common::*;
+
+// Learning progress saved periodically to resume learning
+#[derive(Debug, PartialEq)]
+pub struct Checkpoint {
+    // Number of completed iterations
+    pub iteration: u32,
+    // Minibatch sampling is seeded by seed and iteration (or epoch)
+    pub seed: u64,
+    // Learning rates before scaled by the scheduler
+    pub base_learning_rates: Vec<f64>,
+    // Test results before learning and after each iteration
+    pub losses: Vec<f64>,
+    pub correct_rates: Vec<f64>,
+    // Validation results of each evaluation
+    pub val_losses: Vec<f64>,
+    pub val_correct_rates: Vec<f64>,
+}
+
+pub struct CheckpointParameter {
+    // Save a checkpoint every `interval` iterations
+    pub interval: u32,
+    pub file_path: String,
+}
+
+impl Checkpoint {
+    pub fn checkpoint_label() -> &'static str {
+        "checkpoint"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        let iteration = next_import_value(lines)?.parse::<u32>()?;
+        let seed = next_import_value(lines)?.parse::<u64>()?;
+        let base_learning_rates = parse_f64_line(next_import_value(lines)?)?;
+        let losses = parse_f64_line(next_import_value(lines)?)?;
+        let correct_rates = parse_f64_line(next_import_value(lines)?)?;
+        let val_losses = parse_f64_line(next_import_value(lines)?)?;
+        let val_correct_rates = parse_f64_line(next_import_value(lines)?)?;
+
+        Ok(
+            Checkpoint {
+                iteration: iteration,
+                seed: seed,
+                base_learning_rates: base_learning_rates,
+                losses: losses,
+                correct_rates: correct_rates,
+                val_losses: val_losses,
+                val_correct_rates: val_correct_rates,
+            }
+        )
+    }
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::checkpoint_label())?;
+        writeln!(file, "{}", self.iteration)?;
+        writeln!(file, "{}", self.seed)?;
+        write_f64_line(file, &self.base_learning_rates)?;
+        write_f64_line(file, &self.losses)?;
+        write_f64_line(file, &self.correct_rates)?;
+        write_f64_line(file, &self.val_losses)?;
+        write_f64_line(file, &self.val_correct_rates)?;
+        Ok(())
+    }
+}
+
+#[cfg (not (target_family = "wasm"))]
+fn write_f64_line(file: &mut dyn Write, values: &[f64]) -> Result<(), Box<dyn std::error::Error>> {
+    for v in values {
+        write!(file, "{},", v)?;
+    }
+    writeln!(file, "")?;
+    Ok(())
+}
+
+fn parse_f64_line(line: &str) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    line.split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<f64>().map_err(|e| e.into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export_import() {
+        let checkpoint = Checkpoint {
+            iteration: 3,
+            seed: u64::MAX,
+            base_learning_rates: vec![0.1, 0.01],
+            losses: vec![2.3, 1.5, 1.2, 0.9],
+            correct_rates: vec![0.1, 0.5, 0.6, 0.7],
+            val_losses: vec![1.3],
+            val_correct_rates: vec![],
+        };
+
+        let mut buffer = Vec::<u8>::new();
+        checkpoint.export(&mut buffer).unwrap();
+
+        let data = String::from_utf8(buffer).unwrap();
+        let mut lines = data.lines();
+        assert_eq!(lines.next().unwrap(), Checkpoint::checkpoint_label());
+        assert_eq!(Checkpoint::import(&mut lines).unwrap(), checkpoint);
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_parse_f64_line() {
+        assert_eq!(parse_f64_line("").unwrap(), Vec::<f64>::new());
+        assert_eq!(parse_f64_line("0.5,-1,").unwrap(), vec![0.5, -1.0]);
+    }
+}
\ No newline at end of file
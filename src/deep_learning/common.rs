@@ -99,8 +99,11 @@ pub fn print_img(img: &Array1<f64>) {
 }
 
 pub fn random_choice(size: usize, max: usize) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
+    return random_choice_with_rng(size, max, &mut rand::thread_rng());
+}
 
+// Reproducible with a seeded rng
+pub fn random_choice_with_rng<R: Rng>(size: usize, max: usize, rng: &mut R) -> Vec<usize> {
     let mut choice = Vec::<usize>::with_capacity(size as usize);
     for i in 0..size {
         choice.push((rng.gen::<f32>()*max as f32).floor() as usize);
@@ -125,6 +128,14 @@ pub fn norm_random_vec(num: usize) -> Vec<f64> {
     return y;
 }
 
+// Take the next value of exported data, or an error if the data is truncated
+pub fn next_import_value<'a, I: Iterator<Item = &'a str>>(values: &mut I) -> Result<&'a str, Box<dyn std::error::Error>> {
+    match values.next() {
+        Some(value) => Ok(value),
+        None => Err("Unexpected end of data".into()),
+    }
+}
+
 
 #[cfg(test)]
 mod test {
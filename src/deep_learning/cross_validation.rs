@@ -0,0 +1,333 @@
+use std::fmt;
+use ndarray::prelude::{
+    Array2,
+};
+use rand::{
+    SeedableRng,
+    rngs::StdRng,
+    seq::SliceRandom,
+};
+
+use crate::deep_learning::history::*;
+use crate::deep_learning::neural_network::*;
+use crate::deep_learning::statistics::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoldMetric {
+    Loss,
+    CorrectRate,
+    MacroPrecision,
+    MacroRecall,
+    MacroF1,
+}
+impl fmt::Display for FoldMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FoldMetric::Loss => "Loss",
+            FoldMetric::CorrectRate => "CorrectRate",
+            FoldMetric::MacroPrecision => "MacroPrecision",
+            FoldMetric::MacroRecall => "MacroRecall",
+            FoldMetric::MacroF1 => "MacroF1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct FoldResult {
+    pub fold: usize,
+    pub history: TrainingHistory,
+    // Evaluation of the held out fold
+    pub result: EvaluationResult,
+}
+impl FoldResult {
+    pub fn metric(&self, metric: FoldMetric) -> f64 {
+        match metric {
+            FoldMetric::Loss => self.result.loss,
+            FoldMetric::CorrectRate => self.result.correct_rate,
+            FoldMetric::MacroPrecision => self.result.metrics.macro_precision(),
+            FoldMetric::MacroRecall => self.result.metrics.macro_recall(),
+            FoldMetric::MacroF1 => self.result.metrics.macro_f1(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSummary {
+    pub metric: FoldMetric,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+pub struct CrossValidationResult {
+    pub folds: Vec<FoldResult>,
+    pub summaries: Vec<MetricSummary>,
+}
+impl CrossValidationResult {
+    pub fn summary(&self, metric: FoldMetric) -> Option<&MetricSummary> {
+        self.summaries.iter().find(|summary| summary.metric == metric)
+    }
+}
+impl fmt::Display for CrossValidationResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}-fold cross validation", self.folds.len())?;
+        for summary in &self.summaries {
+            writeln!(f, "{}: {} ± {}", summary.metric, summary.mean, summary.std_dev)?;
+        }
+        Ok(())
+    }
+}
+
+// Split row indexes into k folds. Sizes differ by at most one.
+// Shuffle before splitting if seed is set.
+pub fn make_folds(data_num: usize, k: usize, seed: Option<u64>) -> Vec<Vec<usize>> {
+    if k < 2 || k > data_num {
+        panic!("Invalid fold num. k: {} data_num: {}", k, data_num);
+    }
+
+    let mut indexes: Vec<usize> = (0..data_num).collect();
+    if let Some(seed) = seed {
+        indexes.shuffle(&mut StdRng::seed_from_u64(seed));
+    }
+
+    let mut folds = Vec::<Vec<usize>>::new();
+    let mut start = 0;
+    for fold_i in 0..k {
+        let fold_size = data_num / k + if fold_i < data_num % k {1} else {0};
+        folds.push(indexes[start..start + fold_size].to_vec());
+        start += fold_size;
+    }
+    return folds;
+}
+
+// Training rows and inner validation rows for the held out fold.
+// The inner validation rows are the tail of the training folds, as many as a k-th of them (at least one).
+// Without inner validation, all rows except the held out fold are training rows.
+pub fn split_fold(folds: &[Vec<usize>], fold_i: usize, inner_validation: bool) -> (Vec<usize>, Vec<usize>) {
+    let mut trn_indexes: Vec<usize> = folds.iter()
+        .enumerate()
+        .filter(|(i, _)| *i != fold_i)
+        .flat_map(|(_, fold)| fold.iter().cloned())
+        .collect();
+    if !inner_validation {
+        return (trn_indexes, Vec::new());
+    }
+    if trn_indexes.len() < 2 {
+        panic!("Too few training rows to split validation rows. training rows: {}", trn_indexes.len());
+    }
+    let val_num = (trn_indexes.len() / folds.len()).max(1);
+    let val_indexes = trn_indexes.split_off(trn_indexes.len() - val_num);
+    return (trn_indexes, val_indexes);
+}
+
+// Train k networks, each on k-1 folds, and evaluate each on the held out fold.
+// The held out fold is never given to learn. If validation or a scheduler is set,
+// they watch inner validation rows split from the training folds (see split_fold).
+// Otherwise the test data of learn is the training data, only to show the progress.
+// make_network and make_parameter are called with the fold index.
+pub fn cross_validate<N, P>(
+    k: usize,
+    data: &Array2<f64>,
+    lbl_onehot: &Array2<f64>,
+    seed: Option<u64>,
+    metrics: &[FoldMetric],
+    mut make_network: N,
+    mut make_parameter: P,
+) -> CrossValidationResult
+where
+    N: FnMut(usize) -> NeuralNetwork,
+    P: FnMut(usize) -> LearningParameter,
+{
+    let folds = make_folds(data.shape()[0], k, seed);
+
+    let mut fold_results = Vec::<FoldResult>::new();
+    for fold_i in 0..k {
+        let mut nn = make_network(fold_i);
+        let parameter = make_parameter(fold_i);
+        let batch_size = parameter.batch_size;
+
+        let inner_validation = parameter.validation.is_some() || parameter.scheduler.is_some();
+        let (trn_indexes, val_indexes) = split_fold(&folds, fold_i, inner_validation);
+        let (trn_data, trn_lbl_onehot) = make_minibatch_data_from_indexes(&trn_indexes, data, lbl_onehot);
+        let (val_data, val_lbl_onehot) = if inner_validation {
+            make_minibatch_data_from_indexes(&val_indexes, data, lbl_onehot)
+        } else {
+            (trn_data.clone(), trn_lbl_onehot.clone())
+        };
+        let (tst_data, tst_lbl_onehot) = make_minibatch_data_from_indexes(&folds[fold_i], data, lbl_onehot);
+
+        let history = nn.learn(
+            parameter,
+            LearningResource {
+                trn_data: trn_data,
+                trn_lbl_onehot: trn_lbl_onehot,
+                tst_data: val_data.clone(),
+                tst_lbl_onehot: val_lbl_onehot.clone(),
+                val_data: val_data,
+                val_lbl_onehot: val_lbl_onehot,
+            }
+        );
+        let result = nn.evaluate(batch_size, &tst_data, &tst_lbl_onehot);
+
+        fold_results.push(FoldResult {
+            fold: fold_i,
+            history: history,
+            result: result,
+        });
+    }
+
+    let summaries = metrics.iter().map(|metric| {
+        let values: Vec<f64> = fold_results.iter().map(|fold| fold.metric(*metric)).collect();
+        let (std_dev, _, avg) = standard_devication(&values);
+        MetricSummary {
+            metric: *metric,
+            mean: avg,
+            std_dev: std_dev,
+        }
+    }).collect();
+
+    return CrossValidationResult {
+        folds: fold_results,
+        summaries: summaries,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+    use crate::deep_learning::layer::*;
+    use crate::deep_learning::optimizer::*;
+
+    #[test]
+    fn test_make_folds() {
+        let folds = make_folds(7, 3, None);
+
+        assert_eq!(folds, vec![vec![0, 1, 2], vec![3, 4], vec![5, 6]]);
+
+        let folds = make_folds(7, 3, Some(0));
+        let mut indexes: Vec<usize> = folds.iter().flat_map(|fold| fold.iter().cloned()).collect();
+        indexes.sort();
+        assert_eq!(indexes, (0..7).collect::<Vec<usize>>());
+        assert_eq!(folds.iter().map(|fold| fold.len()).collect::<Vec<usize>>(), vec![3, 2, 2]);
+        // Same seed, same folds
+        assert_eq!(folds, make_folds(7, 3, Some(0)));
+    }
+
+    #[test]
+    fn test_cross_validate() {
+        let batch_size = 2;
+
+        let data = Array2::<f64>::from_shape_fn((9, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((9, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let mut network_folds = Vec::<usize>::new();
+        let result = cross_validate(
+            3,
+            &data,
+            &lbl_onehot,
+            Some(0),
+            &[FoldMetric::Loss, FoldMetric::CorrectRate],
+            |fold_i| {
+                network_folds.push(fold_i);
+                let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+                let layers = Affine::new_random(
+                    layers,
+                    3,
+                    2,
+                    Sgd::new(0.1),
+                    Sgd::new(0.1)
+                );
+                let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+                let mut nn = NeuralNetwork::new(layers);
+                nn.set_quiet(true);
+                nn
+            },
+            |_| LearningParameter {
+                batch_size: batch_size,
+                iterations_num: 3,
+                // Made from the training rows of each fold
+                sampling: MinibatchSampling::ClassBalanced,
+                gradient_clipping: None,
+                scheduler: None,
+                seed: Some(0),
+                checkpoint: None,
+                validation: None,
+                callbacks: Vec::new(),
+                threads_num: 1,
+            },
+        );
+
+        assert_eq!(network_folds, vec![0, 1, 2]);
+        assert_eq!(result.folds.len(), 3);
+        for fold in &result.folds {
+            assert_eq!(fold.result.sample_num, 3);
+            assert_eq!(fold.history.iterations.len(), 3);
+        }
+        assert_eq!(result.summaries.len(), 2);
+
+        let losses: Vec<f64> = result.folds.iter().map(|fold| fold.result.loss).collect();
+        let (std_dev, _, avg) = standard_devication(&losses);
+        let summary = result.summary(FoldMetric::Loss).unwrap();
+        assert_eq!(round_digit(summary.mean, -6), round_digit(avg, -6));
+        assert_eq!(round_digit(summary.std_dev, -6), round_digit(std_dev, -6));
+        assert!(result.summary(FoldMetric::MacroF1).is_none());
+    }
+
+    #[test]
+    fn test_split_fold() {
+        let folds = vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8]];
+
+        assert_eq!(split_fold(&folds, 1, false), (vec![0, 1, 2, 6, 7, 8], Vec::new()));
+        // A third of the 6 training rows from the tail
+        assert_eq!(split_fold(&folds, 1, true), (vec![0, 1, 2, 6], vec![7, 8]));
+        // Two folds
+        assert_eq!(split_fold(&folds[..2], 0, true), (vec![3, 4], vec![5]));
+    }
+
+    #[test]
+    fn test_cross_validate_two_folds_with_validation() {
+        let batch_size = 2;
+
+        let data = Array2::<f64>::from_shape_fn((8, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((8, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let result = cross_validate(
+            2,
+            &data,
+            &lbl_onehot,
+            Some(0),
+            &[FoldMetric::Loss],
+            |_| {
+                let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+                let layers = Affine::new_random(layers, 3, 2, Sgd::new(0.1), Sgd::new(0.1));
+                let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+                let mut nn = NeuralNetwork::new(layers);
+                nn.set_quiet(true);
+                nn
+            },
+            |_| LearningParameter {
+                batch_size: batch_size,
+                iterations_num: 2,
+                sampling: MinibatchSampling::WithReplacement,
+                gradient_clipping: None,
+                scheduler: None,
+                seed: Some(0),
+                checkpoint: None,
+                validation: Some(ValidationParameter {
+                    interval: 1,
+                    early_stopping: None,
+                    restore_best_weights: true,
+                }),
+                callbacks: Vec::new(),
+                threads_num: 1,
+            },
+        );
+
+        assert_eq!(result.folds.len(), 2);
+        for fold in &result.folds {
+            assert_eq!(fold.result.sample_num, 4);
+            assert_eq!(fold.history.evaluations.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+use std::thread::{
+    self,
+    JoinHandle,
+};
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::layer::*;
+use crate::deep_learning::neural_network::*;
+
+// Minibatch shard and the current parameter values sent to a worker
+struct Job {
+    parameter_values: Vec<Array2<f64>>,
+    data: Array2<f64>,
+    lbl_onehot: Array2<f64>,
+}
+
+// Gradients in visit_parameters order and the loss summed over the shard
+struct ShardResult {
+    gradients: Vec<Option<Array2<f64>>>,
+    loss_sum: f64,
+}
+
+struct Worker {
+    sender: Option<Sender<Job>>,
+    receiver: Receiver<ShardResult>,
+    handle: Option<JoinHandle<()>>,
+}
+
+// Threads each owning a copy of the network.
+// A minibatch is split into shards and each worker runs forward/backward on one of them.
+// Networks with BatchNorm are rejected because each worker would normalize over its shard only.
+pub struct DataParallel {
+    workers: Vec<Worker>,
+}
+impl DataParallel {
+    #[cfg (not (target_family = "wasm"))]
+    pub fn new(network: &mut NeuralNetwork, threads_num: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        if threads_num == 0 {
+            panic!("threads_num must be greater than 0");
+        }
+        let mut has_batch_norm = false;
+        network.visit_parameters(|name, _| {
+            has_batch_norm |= parameter_layer_label(name) == BatchNorm::layer_label();
+        });
+        if has_batch_norm {
+            return Err("BatchNorm can not be split into shards".into());
+        }
+        // Layers can not be sent to other threads. Each worker imports the exported network.
+        let exported = network.export_to_string()?;
+
+        let mut workers = Vec::<Worker>::new();
+        for _ in 0..threads_num {
+            let (job_sender, job_receiver) = channel::<Job>();
+            let (result_sender, result_receiver) = channel::<ShardResult>();
+            let exported = exported.clone();
+            let handle = thread::spawn(move || {
+                let mut network = NeuralNetwork::import(&exported);
+                network.set_quiet(true);
+                // Finish when the sender is dropped
+                for job in job_receiver {
+                    network.set_parameter_values(&job.parameter_values);
+                    network.zero_grad();
+                    let loss_sum = network.compute_gradient(&job.data, &job.lbl_onehot) * job.data.shape()[0] as f64;
+
+                    let mut gradients = Vec::<Option<Array2<f64>>>::new();
+                    network.visit_parameters(|_, parameter| {
+                        gradients.push(parameter.get_gradient().cloned());
+                    });
+                    if result_sender.send(ShardResult { gradients: gradients, loss_sum: loss_sum }).is_err() {
+                        break;
+                    }
+                }
+            });
+            workers.push(Worker {
+                sender: Some(job_sender),
+                receiver: result_receiver,
+                handle: Some(handle),
+            });
+        }
+
+        Ok(
+            DataParallel {
+                workers: workers,
+            }
+        )
+    }
+
+    // Networks can not be exported to the workers
+    #[cfg (target_family = "wasm")]
+    pub fn new(_network: &mut NeuralNetwork, _threads_num: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("Threads are not supported on wasm".into())
+    }
+
+    pub fn threads_num(&self) -> usize {
+        self.workers.len()
+    }
+
+    // Replace the gradients of network with the gradients of the whole minibatch and return the mean loss.
+    // Gradients of each shard are sums over its rows, so the sum over shards equals the single thread gradient.
+    pub fn compute_gradient(&mut self, network: &mut NeuralNetwork, batch_data: &Array2<f64>, batch_lbl_onehot: &Array2<f64>) -> f64 {
+        let parameter_values = network.parameter_values();
+
+        let shards = split_shards(batch_data.shape()[0], self.workers.len());
+        for (worker, indexes) in self.workers.iter().zip(shards.iter()) {
+            let (data, lbl_onehot) = make_minibatch_data_from_indexes(indexes, batch_data, batch_lbl_onehot);
+            let job = Job {
+                parameter_values: parameter_values.clone(),
+                data: data,
+                lbl_onehot: lbl_onehot,
+            };
+            worker.sender.as_ref().unwrap().send(job).expect("Data parallel worker stopped");
+        }
+
+        network.zero_grad();
+        let mut loss_sum = 0f64;
+        for worker in self.workers.iter().take(shards.len()) {
+            let result = worker.receiver.recv().expect("Data parallel worker stopped");
+            loss_sum += result.loss_sum;
+
+            let mut gradients = result.gradients.into_iter();
+            network.visit_parameters(|_, parameter| {
+                // Accumulated by backward
+                if let Some(Some(gradient)) = gradients.next() {
+                    parameter.backward(gradient);
+                }
+            });
+        }
+
+        return loss_sum / batch_data.shape()[0] as f64;
+    }
+}
+impl Drop for DataParallel {
+    fn drop(&mut self) {
+        for worker in self.workers.iter_mut() {
+            worker.sender = None;
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+// Split rows into at most shards_num contiguous shards. Sizes differ by at most one.
+pub fn split_shards(data_num: usize, shards_num: usize) -> Vec<Vec<usize>> {
+    let shards_num = shards_num.min(data_num);
+    let mut shards = Vec::<Vec<usize>>::new();
+    let mut start = 0;
+    for shard_i in 0..shards_num {
+        let shard_size = data_num / shards_num + if shard_i < data_num % shards_num {1} else {0};
+        shards.push((start..start + shard_size).collect());
+        start += shard_size;
+    }
+    return shards;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+    use crate::deep_learning::optimizer::*;
+
+    fn make_network(batch_size: usize) -> NeuralNetwork {
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = Relu::new(layers);
+        let layers = Affine::new_random(
+            layers,
+            4,
+            2,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        NeuralNetwork::new(layers)
+    }
+
+    fn gradients(network: &mut NeuralNetwork) -> Vec<Array2<f64>> {
+        let mut gradients = Vec::<Array2<f64>>::new();
+        network.visit_parameters(|_, parameter| {
+            gradients.push(round_digit_arr2(parameter.get_gradient().unwrap(), -8));
+        });
+        return gradients;
+    }
+
+    #[test]
+    fn test_split_shards() {
+        assert_eq!(split_shards(7, 3), vec![vec![0, 1, 2], vec![3, 4], vec![5, 6]]);
+        // Fewer rows than shards
+        assert_eq!(split_shards(2, 4), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn test_compute_gradient() {
+        let batch_size = 5;
+        let data = Array2::<f64>::from_shape_fn((batch_size, 3), |(i, j)| (i * 3 + j) as f64 / 10.0 - 0.7);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((batch_size, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let mut nn = make_network(batch_size);
+        nn.zero_grad();
+        let loss = nn.compute_gradient(&data, &lbl_onehot);
+        let expected = gradients(&mut nn);
+
+        let mut data_parallel = DataParallel::new(&mut nn, 3).unwrap();
+        assert_eq!(data_parallel.threads_num(), 3);
+        let parallel_loss = data_parallel.compute_gradient(&mut nn, &data, &lbl_onehot);
+
+        assert_eq!(round_digit(parallel_loss, -8), round_digit(loss, -8));
+        assert_eq!(gradients(&mut nn), expected);
+    }
+
+    #[test]
+    fn test_learn() {
+        let batch_size = 4;
+        let data = Array2::<f64>::from_shape_fn((8, 3), |(i, j)| (i * 3 + j) as f64 / 10.0 - 1.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((8, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let mut single = make_network(batch_size);
+        single.set_quiet(true);
+        let mut parallel = NeuralNetwork::import(&single.export_to_string().unwrap());
+        parallel.set_quiet(true);
+
+        let parameter = |threads_num| LearningParameter {
+            batch_size: batch_size,
+            iterations_num: 5,
+            sampling: MinibatchSampling::WithReplacement,
+            gradient_clipping: None,
+            scheduler: None,
+            seed: Some(0),
+            checkpoint: None,
+            validation: None,
+            callbacks: Vec::new(),
+            threads_num: threads_num,
+        };
+        let resource = LearningResource {
+            trn_data: data.clone(),
+            trn_lbl_onehot: lbl_onehot.clone(),
+            tst_data: data.clone(),
+            tst_lbl_onehot: lbl_onehot.clone(),
+            val_data: data.clone(),
+            val_lbl_onehot: lbl_onehot.clone(),
+        };
+        let single_history = single.learn(parameter(1), resource.clone());
+        let parallel_history = parallel.learn(parameter(2), resource.clone());
+
+        let round = |values: Vec<Array2<f64>>| values.iter().map(|v| round_digit_arr2(v, -8)).collect::<Vec<Array2<f64>>>();
+        assert_eq!(round(parallel.parameter_values()), round(single.parameter_values()));
+        for (s, p) in single_history.iterations.iter().zip(parallel_history.iterations.iter()) {
+            assert_eq!(round_digit(p.train_loss, -8), round_digit(s.train_loss, -8));
+        }
+    }
+
+    #[test]
+    fn test_new_batch_norm() {
+        let batch_size = 4;
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = BatchNorm::new(
+            layers,
+            NetworkBatchNormValueLayer::new(Array2::<f64>::ones((1, 3)), Sgd::new(0.1)),
+            NetworkBatchNormValueLayer::new(Array2::<f64>::zeros((1, 3)), Sgd::new(0.1)),
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 3)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        assert!(DataParallel::new(&mut nn, 2).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// Stop learning when validation loss has not improved by more than min_delta for patience evaluations
+pub struct EarlyStopping {
+    patience: u32,
+    min_delta: f64,
+    best_loss: Option<f64>,
+    wait: u32,
+}
+impl EarlyStopping {
+    pub fn new(patience: u32, min_delta: f64) -> Self {
+        EarlyStopping {
+            patience: patience,
+            min_delta: min_delta,
+            best_loss: None,
+            wait: 0,
+        }
+    }
+    // Return true if the loss improved
+    pub fn observe_loss(&mut self, loss: f64) -> bool {
+        let improved = match self.best_loss {
+            Some(best_loss) => loss < best_loss - self.min_delta,
+            None => true,
+        };
+        if improved {
+            self.best_loss = Some(loss);
+            self.wait = 0;
+        } else {
+            self.wait += 1;
+        }
+        return improved;
+    }
+    // Only after a loss without improvement, so patience 0 stops at the first one
+    pub fn should_stop(&self) -> bool {
+        self.wait > 0 && self.wait >= self.patience
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_should_stop() {
+        let mut early_stopping = EarlyStopping::new(2, 0.01);
+
+        assert_eq!(early_stopping.observe_loss(1.0), true);
+        assert_eq!(early_stopping.observe_loss(0.9), true);
+        // Within min_delta
+        assert_eq!(early_stopping.observe_loss(0.895), false);
+        assert_eq!(early_stopping.should_stop(), false);
+        assert_eq!(early_stopping.observe_loss(0.8), true);
+        assert_eq!(early_stopping.observe_loss(0.85), false);
+        assert_eq!(early_stopping.should_stop(), false);
+        assert_eq!(early_stopping.observe_loss(0.8), false);
+        assert_eq!(early_stopping.should_stop(), true);
+    }
+
+    #[test]
+    fn test_should_stop_zero_patience() {
+        let mut early_stopping = EarlyStopping::new(0, 0.0);
+
+        assert_eq!(early_stopping.observe_loss(1.0), true);
+        assert_eq!(early_stopping.should_stop(), false);
+        assert_eq!(early_stopping.observe_loss(0.9), true);
+        assert_eq!(early_stopping.should_stop(), false);
+        assert_eq!(early_stopping.observe_loss(0.9), false);
+        assert_eq!(early_stopping.should_stop(), true);
+    }
+}
@@ -0,0 +1,192 @@
+use ndarray::prelude::{
+    Array2,
+    Axis,
+};
+
+use crate::deep_learning::common::*;
+use crate::deep_learning::layer::*;
+use crate::deep_learning::neural_network::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnsembleMethod {
+    // Mean of softmax outputs
+    Average,
+    // Fraction of networks predicting each class
+    Vote,
+}
+
+// Networks combined into one prediction
+pub struct Ensemble {
+    networks: Vec<NeuralNetwork>,
+    method: EnsembleMethod,
+}
+impl Ensemble {
+    pub fn new(networks: Vec<NeuralNetwork>, method: EnsembleMethod) -> Self {
+        if networks.is_empty() {
+            panic!("Ensemble needs at least one network");
+        }
+        Ensemble {
+            networks: networks,
+            method: method,
+        }
+    }
+    pub fn import_from_files(file_paths: &[&str], method: EnsembleMethod) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut networks = Vec::<NeuralNetwork>::new();
+        for file_path in file_paths {
+            networks.push(NeuralNetwork::import_from_file(file_path)?);
+        }
+        Ok(Self::new(networks, method))
+    }
+    pub fn get_networks(&self) -> &Vec<NeuralNetwork> {&self.networks}
+    pub fn get_method(&self) -> EnsembleMethod {self.method}
+    // Quiet when every network is quiet
+    pub fn set_quiet(&mut self, quiet: bool) {
+        for network in self.networks.iter_mut() {
+            network.set_quiet(quiet);
+        }
+    }
+    pub fn is_quiet(&self) -> bool {
+        self.networks.iter().all(|network| network.is_quiet())
+    }
+
+    // Combined class probabilities of each row
+    pub fn guess(&mut self, batch_data: &Array2<f64>) -> Array2<f64> {
+        let mut combined: Option<Array2<f64>> = None;
+        for network in self.networks.iter_mut() {
+            let probabilities = softmax(&network.guess(batch_data));
+            let contribution = match self.method {
+                EnsembleMethod::Average => probabilities,
+                EnsembleMethod::Vote => {
+                    let mut votes = Array2::<f64>::zeros(probabilities.dim());
+                    for (row_i, row) in probabilities.axis_iter(Axis(0)).enumerate() {
+                        votes[(row_i, max_index_in_arr1(&row.to_owned()))] = 1.0;
+                    }
+                    votes
+                },
+            };
+            combined = match combined {
+                Some(combined) => Some(combined + contribution),
+                None => Some(contribution),
+            };
+        }
+        return combined.unwrap() / self.networks.len() as f64;
+    }
+
+    // Same as NeuralNetwork::test. Loss is cross entropy of the combined probabilities.
+    pub fn test(&mut self, batch_size: usize, tst_data: &Array2<f64>, tst_lbl_onehot: &Array2<f64>) -> (f64, f64) {
+        let quiet = self.is_quiet();
+        return test_minibatches(batch_size, tst_data, tst_lbl_onehot, quiet, |batch_data, batch_lbl_onehot| {
+            self.guess_with_loss(batch_data, batch_lbl_onehot)
+        });
+    }
+
+    // Same as NeuralNetwork::evaluate
+    pub fn evaluate(&mut self, batch_size: usize, data: &Array2<f64>, lbl_onehot: &Array2<f64>) -> EvaluationResult {
+        return evaluate_minibatches(batch_size, data, lbl_onehot, |batch_data, batch_lbl_onehot| {
+            self.guess_with_loss(batch_data, batch_lbl_onehot)
+        });
+    }
+
+    fn guess_with_loss(&mut self, batch_data: &Array2<f64>, batch_lbl_onehot: &Array2<f64>) -> (Array2<f64>, Array2<f64>) {
+        let res = self.guess(batch_data);
+        let loss = crosss_entropy_error(&res, batch_lbl_onehot);
+        return (res, loss);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    use crate::deep_learning::optimizer::*;
+
+    // Network outputting x * w
+    fn make_network(w: Array2<f64>) -> NeuralNetwork {
+        let batch_size = 3;
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 2)));
+        let layers = Affine::new(
+            layers,
+            AffineDirectValue::new(w, Sgd::new(0.1)),
+            AffineDirectValue::new(Array2::<f64>::zeros((1, 2)), Sgd::new(0.1)),
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+        nn.set_quiet(true);
+        nn
+    }
+
+    fn make_ensemble(method: EnsembleMethod) -> Ensemble {
+        let networks = vec![
+            // Predict class 0
+            make_network(arr2(&[[3.0, 0.0], [3.0, 0.0]])),
+            // Predict class 1 weakly
+            make_network(arr2(&[[0.0, 0.2], [0.0, 0.2]])),
+            make_network(arr2(&[[0.0, 0.2], [0.0, 0.2]])),
+        ];
+        let mut ensemble = Ensemble::new(networks, method);
+        ensemble.set_quiet(true);
+        ensemble
+    }
+
+    #[test]
+    fn test_guess_average() {
+        let mut ensemble = make_ensemble(EnsembleMethod::Average);
+        let data = arr2(&[[1.0, 1.0], [2.0, 0.0]]);
+
+        let res = ensemble.guess(&data);
+
+        let mut expected = Array2::<f64>::zeros((2, 2));
+        for network in ensemble.networks.iter_mut() {
+            expected = expected + softmax(&network.guess(&data));
+        }
+        expected /= 3.0;
+        assert_eq!(round_digit_arr2(&res, -6), round_digit_arr2(&expected, -6));
+        // Confident network 0 wins the average
+        assert_eq!(max_index_in_arr1(&res.index_axis(Axis(0), 0).to_owned()), 0);
+    }
+
+    #[test]
+    fn test_guess_vote() {
+        let mut ensemble = make_ensemble(EnsembleMethod::Vote);
+        let data = arr2(&[[1.0, 1.0], [2.0, 0.0]]);
+
+        let res = ensemble.guess(&data);
+
+        // Majority wins the vote
+        assert_eq!(round_digit_arr2(&res, -6), round_digit_arr2(&arr2(&[[1.0 / 3.0, 2.0 / 3.0], [1.0 / 3.0, 2.0 / 3.0]]), -6));
+    }
+
+    #[test]
+    fn test_test_and_evaluate() {
+        let mut ensemble = make_ensemble(EnsembleMethod::Vote);
+        let data = arr2(&[[1.0, 1.0], [2.0, 0.0], [0.0, 1.0], [1.0, 0.0], [3.0, 3.0]]);
+        let lbl_onehot = arr2(&[[0.0, 1.0], [0.0, 1.0], [1.0, 0.0], [0.0, 1.0], [1.0, 0.0]]);
+
+        // Every row is voted [1/3, 2/3], so random minibatches give the same loss and correct rate
+        let data_num = 4;
+        let same_data = Array2::<f64>::ones((data_num, 2));
+        let (loss, correct_rate) = ensemble.test(2, &same_data, &arr2(&[[0.0, 1.0]]).broadcast((data_num, 2)).unwrap().to_owned());
+        assert_eq!(round_digit(loss, -6), round_digit(-(2.0f64 / 3.0 + 0.00000001).ln(), -6));
+        assert_eq!(round_digit(correct_rate, -6), 1.0);
+        let (loss, correct_rate) = ensemble.test(2, &same_data, &arr2(&[[1.0, 0.0]]).broadcast((data_num, 2)).unwrap().to_owned());
+        assert_eq!(round_digit(loss, -6), round_digit(-(1.0f64 / 3.0 + 0.00000001).ln(), -6));
+        assert_eq!(round_digit(correct_rate, -6), 0.0);
+
+        // Average of softmax([6, 0]) and two softmax([0, 0.4]) for [1, 1]
+        let mut average_ensemble = make_ensemble(EnsembleMethod::Average);
+        let class0_probability = (6f64.exp() / (6f64.exp() + 1.0) + 2.0 / (1.0 + 0.4f64.exp())) / 3.0;
+        let (loss, correct_rate) = average_ensemble.test(2, &same_data, &arr2(&[[1.0, 0.0]]).broadcast((data_num, 2)).unwrap().to_owned());
+        assert_eq!(round_digit(loss, -6), round_digit(-(class0_probability + 0.00000001).ln(), -6));
+        assert_eq!(round_digit(correct_rate, -6), 1.0);
+
+        let result = ensemble.evaluate(2, &data, &lbl_onehot);
+        assert_eq!(result.sample_num, 5);
+        assert_eq!(round_digit(result.correct_rate, -6), 0.6);
+        let expected_loss = (3.0 * -(2.0f64 / 3.0 + 0.00000001).ln() + 2.0 * -(1.0f64 / 3.0 + 0.00000001).ln()) / 5.0;
+        assert_eq!(round_digit(result.loss, -6), round_digit(expected_loss, -6));
+    }
+}
@@ -0,0 +1,278 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use ndarray::prelude::{
+    Array1,
+    Array2,
+};
+
+use crate::deep_learning::common::*;
+use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
+
+const INPUT_NAME: &str = "input";
+
+pub struct GradientCheckResult {
+    pub name: String,
+    pub analytical_gradient: Array2<f64>,
+    pub numerical_gradient: Array2<f64>,
+    pub max_relative_error: f64,
+}
+
+// Input layer of the checked layers. Gradient given by backward is shared with check_gradient.
+pub struct GradientCheckInput {
+    value: Array2<f64>,
+    gradient: Rc<RefCell<Option<Array2<f64>>>>,
+}
+impl NetworkLayer for GradientCheckInput {
+    fn forward(&mut self, _is_learning: bool) -> Array2<f64> {
+        self.value.clone()
+    }
+    fn backward(&mut self, dout: Array2<f64>) {
+        let mut gradient = self.gradient.borrow_mut();
+        match gradient.as_mut() {
+            Some(gradient) => *gradient += &dout,
+            None => *gradient = Some(dout),
+        }
+    }
+    fn set_value(&mut self, value: &Array2<f64>) {
+        self.value.assign(value);
+    }
+    fn set_lbl(&mut self, _value: &Array2<f64>) {
+        // Nothing to do
+    }
+    fn clean(&mut self) {
+        // Nothing to do
+    }
+    fn plot(&self) {
+        // Nothing to do
+    }
+    fn weight_squared_sum(&self) -> f64 {
+        0f64
+    }
+    fn weight_sum(&self) -> f64 {
+        0f64
+    }
+    fn freeze_except_top(&mut self, _trainable_num: usize) {
+        // Nothing to do
+    }
+    fn visit_parameters(&mut self, _visitor: &mut dyn ParameterVisitor) -> usize {
+        // Input layer
+        0
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        summaries.push(LayerSummary::new("GradientCheckInput", INPUT_NAME, self.value.shape(), (0, 0)));
+    }
+}
+
+// Compare gradients calculated by backward with numerical gradients.
+// `build` receives the input layer and returns the layer stack to check.
+// Checked function is f = Σ(forward ⊙ dout), so backward(dout) should give df/dp for each parameter.
+// The first result is the gradient of the input.
+pub fn check_gradient<F, L>(build: F, input: Array2<f64>, dout: &Array2<f64>) -> Vec<GradientCheckResult>
+    where   F: FnOnce(GradientCheckInput) -> L,
+            L: NetworkLayer
+{
+    let input_gradient = Rc::new(RefCell::new(None));
+    let mut layer = build(GradientCheckInput { value: input.clone(), gradient: input_gradient.clone() });
+
+    // Analytical gradient
+    layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+        parameter.zero_grad();
+    });
+    layer.set_value(&input);
+    layer.forward(true);
+    layer.backward(dout.clone());
+    let mut names = vec![INPUT_NAME.to_string()];
+    let mut analytical_gradients = vec![match input_gradient.borrow().as_ref() {
+        Some(gradient) => gradient.clone(),
+        None => Array2::<f64>::zeros(input.dim()),
+    }];
+    layer.visit_parameters(&mut |name: &str, parameter: &mut dyn Parameter| {
+        names.push(name.to_string());
+        analytical_gradients.push(match parameter.get_gradient() {
+            Some(gradient) => gradient.clone(),
+            None => Array2::<f64>::zeros(parameter.get_value().dim()),
+        });
+    });
+
+    // Numerical gradient and relative error for each tensor
+    let layer = RefCell::new(layer);
+    let mut results = Vec::<GradientCheckResult>::with_capacity(names.len());
+    for (i, (name, analytical_gradient)) in names.into_iter().zip(analytical_gradients.into_iter()).enumerate() {
+        let numerical_gradient = if i == 0 {
+            numeric_arr2_gradient(|x| {
+                let mut layer = layer.borrow_mut();
+                layer.set_value(x);
+                sum_weighted_output(&mut *layer, dout)
+            }, &input)
+        } else {
+            let parameter_i = i - 1;
+            let value = get_parameter_value(&mut *layer.borrow_mut(), parameter_i);
+            let gradient = numeric_arr2_gradient(|x| {
+                let mut layer = layer.borrow_mut();
+                set_parameter_value(&mut *layer, parameter_i, x);
+                // Clear cached forward results
+                layer.set_value(&input);
+                sum_weighted_output(&mut *layer, dout)
+            }, &value);
+            set_parameter_value(&mut *layer.borrow_mut(), parameter_i, &value);
+            gradient
+        };
+        let max_relative_error = max_relative_error(&analytical_gradient, &numerical_gradient);
+        results.push(GradientCheckResult {
+            name: name,
+            analytical_gradient: analytical_gradient,
+            numerical_gradient: numerical_gradient,
+            max_relative_error: max_relative_error,
+        });
+    }
+
+    return results;
+}
+
+pub fn relative_error(a: f64, b: f64) -> f64 {
+    let denominator = a.abs() + b.abs();
+    if denominator < 10f64.powi(-12) {
+        return 0f64;
+    }
+    return (a - b).abs() / denominator;
+}
+
+fn max_relative_error(a: &Array2<f64>, b: &Array2<f64>) -> f64 {
+    let mut max = 0f64;
+    for (a, b) in a.iter().zip(b.iter()) {
+        let error = relative_error(*a, *b);
+        if max < error {
+            max = error;
+        }
+    }
+    return max;
+}
+
+// common::numeric_gradient of a function of a matrix
+fn numeric_arr2_gradient<F>(func: F, x: &Array2<f64>) -> Array2<f64>
+    where F: Fn(&Array2<f64>) -> f64
+{
+    let dim = x.dim();
+    let flat_x = Array1::from(x.iter().cloned().collect::<Vec<f64>>());
+    let gradient = numeric_gradient(|flat_x: &Array1<f64>| func(&flat_x.clone().into_shape(dim).unwrap()), &flat_x);
+    return gradient.into_shape(dim).unwrap();
+}
+
+fn sum_weighted_output<L>(layer: &mut L, dout: &Array2<f64>) -> f64
+    where L: NetworkLayer
+{
+    let y = layer.forward(true);
+    return (y * dout).sum();
+}
+
+fn get_parameter_value<L>(layer: &mut L, parameter_i: usize) -> Array2<f64>
+    where L: NetworkLayer
+{
+    let mut i = 0;
+    let mut value = None;
+    layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+        if i == parameter_i {
+            value = Some(parameter.get_value().clone());
+        }
+        i += 1;
+    });
+    return value.unwrap();
+}
+
+fn set_parameter_value<L>(layer: &mut L, parameter_i: usize, value: &Array2<f64>)
+    where L: NetworkLayer
+{
+    let mut i = 0;
+    layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+        if i == parameter_i {
+            parameter.get_value_mut().assign(value);
+        }
+        i += 1;
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    use crate::deep_learning::optimizer::*;
+
+    #[test]
+    fn test_relative_error() {
+        assert_eq!(relative_error(1.0, 1.0), 0.0);
+        assert_eq!(relative_error(0.0, 0.0), 0.0);
+        assert_eq!(relative_error(1.0, 3.0), 0.5);
+        assert_eq!(relative_error(-1.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_check_gradient_affine() {
+        let input = arr2(&
+            [
+                [1.0,  2.0],
+                [1.0, -2.0]
+            ]
+        );
+        let dout = arr2(&
+            [
+                [0.1, 0.2, 0.3],
+                [0.4, 0.5, 0.6]
+            ]
+        );
+
+        let results = check_gradient(
+            |x| Affine::new_random(x, 2, 3, Sgd::new(0.01), Sgd::new(0.01)),
+            input,
+            &dout
+        );
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["input", "1.affine/w", "1.affine/b"]);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-6), "{}: {}", result.name, result.max_relative_error);
+        }
+        // db = Σ dout
+        assert_eq!(round_digit_arr2(&results[2].analytical_gradient, -6), round_digit_arr2(&arr2(&[[0.5, 0.7, 0.9]]), -6));
+    }
+
+    #[test]
+    fn test_check_gradient_parameter_named_input() {
+        let input = arr2(&
+            [
+                [1.0,  2.0],
+                [1.0, -2.0]
+            ]
+        );
+        let dout = arr2(&
+            [
+                [0.1, 0.2],
+                [0.3, 0.4]
+            ]
+        );
+
+        // A parameter named like the input is checked as a parameter
+        let results = check_gradient(
+            |x| Affine::new(
+                x,
+                AffineDirectValue::new_with_name(arr2(&[[1.0, 2.0], [3.0, 4.0]]), Sgd::new(0.01), "input".to_string()),
+                AffineDirectValue::new(arr2(&[[0.5, -0.5]]), Sgd::new(0.01))
+            ),
+            input.clone(),
+            &dout
+        );
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["input", "1.affine/w", "1.affine/b"]);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-6), "{}: {}", result.name, result.max_relative_error);
+        }
+        // dx = dout · wᵀ, dw = xᵀ · dout
+        assert_eq!(round_digit_arr2(&results[0].analytical_gradient, -6), round_digit_arr2(&arr2(&[[0.5, 1.1], [1.1, 2.5]]), -6));
+        assert_eq!(round_digit_arr2(&results[1].analytical_gradient, -6), round_digit_arr2(&arr2(&[[0.4, 0.6], [-0.4, -0.4]]), -6));
+    }
+}
@@ -1,68 +1,71 @@
 use plotters::prelude::*;
+use plotters::coord::ranged1d::{AsRangedCoord, ValueFormatter};
 use chrono::Local;
 use std::path::Path;
 
 pub fn plot_rate(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // image size
-    let image_width = 1080;
-    let image_height = 720;
-
-    // BitMapBackend for generate file
-    let date_str = Local::now().format("%m_%d_%H_%M_%S%.f").to_string();
-    let file_path_str = "./graph/".to_string() + caption + &date_str + ".png";
-    let root = BitMapBackend::new(
-        Path::new(&file_path_str),
-        (image_width, image_height))
-    .into_drawing_area();
+    let x_range = 0u32..(rates.len() as u32);
+    let y_range = 0f64..1f64;
+    let rates_with_x = (0u32..).zip(rates.into_iter()).collect();
 
-    // Background is white
-    root.fill(&WHITE)?;
-    
-    let caption = "";
-    let font = ("sans-serif", 20);
+    plot_line(rates_with_x, x_range, y_range, "rate", caption)
+}
 
+pub fn plot_loss(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
     let x_range = 0u32..(rates.len() as u32);
-    let y_range = 0f64..1f64;
+    let y_range = 0f64..3f64;
+    let rates_with_x = (0u32..).zip(rates.into_iter()).collect();
 
-    // Graph setting
-    let mut chart = ChartBuilder::on(&root)
-        .caption(caption, font.into_font())
-        .margin(5)
-        .x_label_area_size(30)
-        .y_label_area_size(30)
-        .build_cartesian_2d(
-            x_range.clone(),
-            y_range.clone(),
-        )?;
-    
-    // Draw grid
-    chart.configure_mesh().draw()?;
+    plot_line(rates_with_x, x_range, y_range, "rate", caption)
+}
 
-    // Add x data to rate
-    let rates_with_x = rates.clone().into_iter()
-        .zip(x_range.clone().collect::<Vec<u32>>().into_iter())
-        .map(|(y, x)| (x, y));
+pub fn plot_learning_rate_loss(learning_rates: Vec<f64>, losses: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // Pair learning rate and loss
+    let losses_with_x: Vec<(f64, f64)> = learning_rates.into_iter()
+        .zip(losses.into_iter())
+        .filter(|(_, loss)| loss.is_finite())
+        .collect();
+    if losses_with_x.is_empty() {
+        return Err("No finite loss to plot".into());
+    }
 
-    // Draw rate
-    chart
-        .draw_series(LineSeries::new(
-            rates_with_x,
-            &RED
-        ))?
-        .label("rate")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+    // Learning rate in log scale
+    let min_rate = losses_with_x.iter().map(|(rate, _)| *rate).fold(f64::INFINITY, f64::min);
+    let max_rate = losses_with_x.iter().map(|(rate, _)| *rate).fold(0f64, f64::max);
+    let (min_rate, max_rate) = padded_log_range(min_rate, max_rate);
+    let min_loss = losses_with_x.iter().map(|(_, loss)| *loss).fold(f64::INFINITY, f64::min);
+    let max_loss = losses_with_x.iter().map(|(_, loss)| *loss).fold(f64::NEG_INFINITY, f64::max);
+    let (min_loss, max_loss) = padded_range(min_loss, max_loss);
 
-    // Line setting
-    chart
-        .configure_series_labels()
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
-        .draw()?;
+    plot_line(losses_with_x, (min_rate..max_rate).log_scale(), min_loss..max_loss, "loss", caption)
+}
 
-    Ok(())
+// Range with some width when every value is the same. e.g. only one point recorded
+fn padded_range(min: f64, max: f64) -> (f64, f64) {
+    if min < max {
+        return (min, max);
+    }
+    let pad = if min == 0f64 {1f64} else {min.abs() * 0.5};
+    (min - pad, max + pad)
 }
 
-pub fn plot_loss(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
+// Same as padded_range for a log scale axis of positive values
+fn padded_log_range(min: f64, max: f64) -> (f64, f64) {
+    if min < max {
+        return (min, max);
+    }
+    (min / 10f64, max * 10f64)
+}
+
+// Red line chart of points saved into ./graph/{caption}{date}.png
+fn plot_line<X, Y>(points: Vec<(X::Value, Y::Value)>, x_spec: X, y_spec: Y, label: &str, caption: &str) -> Result<(), Box<dyn std::error::Error>>
+    where   X: AsRangedCoord,
+            Y: AsRangedCoord,
+            X::CoordDescType: ValueFormatter<X::Value>,
+            Y::CoordDescType: ValueFormatter<Y::Value>,
+            X::Value: Clone + 'static,
+            Y::Value: Clone + 'static,
+{
     // image size
     let image_width = 1080;
     let image_height = 720;
@@ -77,13 +80,10 @@ pub fn plot_loss(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::erro
 
     // Background is white
     root.fill(&WHITE)?;
-    
+
     let caption = "";
     let font = ("sans-serif", 20);
 
-    let x_range = 0u32..(rates.len() as u32);
-    let y_range = 0f64..3f64;
-
     // Graph setting
     let mut chart = ChartBuilder::on(&root)
         .caption(caption, font.into_font())
@@ -91,25 +91,20 @@ pub fn plot_loss(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::erro
         .x_label_area_size(30)
         .y_label_area_size(30)
         .build_cartesian_2d(
-            x_range.clone(),
-            y_range.clone(),
+            x_spec,
+            y_spec,
         )?;
-    
+
     // Draw grid
     chart.configure_mesh().draw()?;
 
-    // Add x data to rate
-    let rates_with_x = rates.clone().into_iter()
-        .zip(x_range.clone().collect::<Vec<u32>>().into_iter())
-        .map(|(y, x)| (x, y));
-
-    // Draw rate
+    // Draw line
     chart
         .draw_series(LineSeries::new(
-            rates_with_x,
+            points,
             &RED
         ))?
-        .label("rate")
+        .label(label)
         .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
 
     // Line setting
@@ -255,6 +250,22 @@ mod graph_plotters_test {
         plot_rate(data, "test_rate");
     }
 
+    #[test]
+    fn test_padded_range() {
+        assert_eq!(padded_range(1.0, 2.0), (1.0, 2.0));
+        assert_eq!(padded_range(2.0, 2.0), (1.0, 3.0));
+        assert_eq!(padded_range(0.0, 0.0), (-1.0, 1.0));
+        assert_eq!(padded_log_range(0.1, 1.0), (0.1, 1.0));
+        assert_eq!(padded_log_range(0.1, 0.1), (0.01, 1.0));
+    }
+
+    #[test]
+    fn test_plot_learning_rate_loss_single_point() {
+        std::fs::create_dir_all("./graph").unwrap();
+        plot_learning_rate_loss(vec![0.01], vec![2.3], "test_learning_rate_single").unwrap();
+        assert!(plot_learning_rate_loss(vec![0.01], vec![f64::NAN], "test_learning_rate_nan").is_err());
+    }
+
     #[test]
     fn test_plot_histogram() {
         let data = norm_random_vec(30000);
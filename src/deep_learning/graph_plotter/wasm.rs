@@ -6,6 +6,10 @@ pub fn plot_loss(rates: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+pub fn plot_learning_rate_loss(learning_rates: Vec<f64>, losses: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
+
 pub fn plot_histogram(data: Vec<f64>, caption: &str) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
@@ -0,0 +1,110 @@
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationRecord {
+    pub iteration: u32,
+    // Loss of the learned minibatch
+    pub train_loss: f64,
+    pub test_loss: f64,
+    pub test_correct_rate: f64,
+    // Learning rates in visit_parameters order
+    pub learning_rates: Vec<f64>,
+    // Seconds since learn is called
+    pub elapsed_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationRecord {
+    pub iteration: u32,
+    pub val_loss: f64,
+    pub val_correct_rate: f64,
+}
+
+// Metrics recorded by NeuralNetwork::learn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingHistory {
+    pub iterations: Vec<IterationRecord>,
+    pub evaluations: Vec<EvaluationRecord>,
+}
+
+impl TrainingHistory {
+    pub fn new() -> Self {
+        TrainingHistory {
+            iterations: Vec::new(),
+            evaluations: Vec::new(),
+        }
+    }
+
+    pub fn best_evaluation(&self) -> Option<&EvaluationRecord> {
+        self.evaluations.iter().fold(None, |best: Option<&EvaluationRecord>, evaluation| {
+            match best {
+                Some(best) if best.val_loss <= evaluation.val_loss => Some(best),
+                _ => Some(evaluation),
+            }
+        })
+    }
+
+    pub fn last_iteration(&self) -> Option<&IterationRecord> {
+        self.iterations.last()
+    }
+
+    // Write as JSON. Non-finite losses are written as null and can not be imported.
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    pub fn import(data: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let history = serde_json::from_str::<TrainingHistory>(data)?;
+        return Ok(history);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_best_evaluation() {
+        let mut history = TrainingHistory::new();
+        assert_eq!(history.best_evaluation(), None);
+
+        for (iteration, val_loss) in [(1, 0.5), (3, 0.3), (5, 0.3), (7, 0.4)].iter() {
+            history.evaluations.push(EvaluationRecord {
+                iteration: *iteration,
+                val_loss: *val_loss,
+                val_correct_rate: 0.0,
+            });
+        }
+
+        // First one of the lowest loss
+        assert_eq!(history.best_evaluation().unwrap().iteration, 3);
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut history = TrainingHistory::new();
+        history.iterations.push(IterationRecord {
+            iteration: 0,
+            train_loss: 0.75,
+            test_loss: 0.5,
+            test_correct_rate: 0.25,
+            learning_rates: vec![0.1, 0.01],
+            elapsed_seconds: 1.5,
+        });
+        history.evaluations.push(EvaluationRecord {
+            iteration: 0,
+            val_loss: 0.625,
+            val_correct_rate: 0.125,
+        });
+
+        let mut buffer = Vec::<u8>::new();
+        history.export(&mut buffer).unwrap();
+        let imported = TrainingHistory::import(&String::from_utf8(buffer).unwrap()).unwrap();
+
+        assert_eq!(imported, history);
+        assert!(TrainingHistory::import("not history").is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,337 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+};
+
+use crate::deep_learning::history::*;
+use crate::deep_learning::neural_network::*;
+
+pub enum ParameterSpace {
+    // Candidates. Grid search tries all of them.
+    Values(Vec<f64>),
+    // Sampled uniformly in log scale. Only random search can use it.
+    LogUniform {
+        min: f64,
+        max: f64,
+    },
+}
+
+pub struct SearchSpace {
+    parameters: Vec<(String, ParameterSpace)>,
+}
+impl SearchSpace {
+    pub fn new() -> Self {
+        SearchSpace {
+            parameters: Vec::new(),
+        }
+    }
+    pub fn add(mut self, name: &str, space: ParameterSpace) -> Self {
+        self.parameters.push((name.to_string(), space));
+        self
+    }
+    // All combinations of Values
+    pub fn grid(&self) -> Vec<HyperParameters> {
+        let mut grid = vec![HyperParameters::new()];
+        for (name, space) in &self.parameters {
+            let values = match space {
+                ParameterSpace::Values(values) => values,
+                ParameterSpace::LogUniform { .. } => panic!("LogUniform can not be used in grid search. name: {}", name),
+            };
+            let mut next_grid = Vec::<HyperParameters>::new();
+            for parameters in &grid {
+                for value in values {
+                    let mut parameters = parameters.clone();
+                    parameters.values.push((name.clone(), *value));
+                    next_grid.push(parameters);
+                }
+            }
+            grid = next_grid;
+        }
+        return grid;
+    }
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> HyperParameters {
+        let mut parameters = HyperParameters::new();
+        for (name, space) in &self.parameters {
+            let value = match space {
+                ParameterSpace::Values(values) => values[rng.gen_range(0..values.len())],
+                ParameterSpace::LogUniform { min, max } => {
+                    if *min <= 0.0 || min > max {
+                        panic!("Invalid LogUniform range. name: {} min: {} max: {}", name, min, max);
+                    }
+                    (rng.gen::<f64>() * (max.ln() - min.ln()) + min.ln()).exp()
+                },
+            };
+            parameters.values.push((name.clone(), value));
+        }
+        return parameters;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperParameters {
+    pub values: Vec<(String, f64)>,
+}
+impl HyperParameters {
+    pub fn new() -> Self {
+        HyperParameters {
+            values: Vec::new(),
+        }
+    }
+    pub fn get(&self, name: &str) -> f64 {
+        match self.values.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => *value,
+            None => panic!("Unknown hyper parameter. name: {}", name),
+        }
+    }
+}
+impl fmt::Display for HyperParameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        write!(f, "{}", values.join(" "))
+    }
+}
+
+// Data ranking the trials. Must not be a part of the learning resource.
+pub struct SelectionData<'a> {
+    pub data: &'a Array2<f64>,
+    pub lbl_onehot: &'a Array2<f64>,
+}
+
+pub struct TrialResult {
+    pub parameters: HyperParameters,
+    pub history: TrainingHistory,
+    // Evaluation of the selection data
+    pub result: EvaluationResult,
+}
+
+// Trials ranked by selection loss
+pub struct SearchResult {
+    pub trials: Vec<TrialResult>,
+}
+impl SearchResult {
+    pub fn best(&self) -> Option<&TrialResult> {
+        self.trials.first()
+    }
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export_table(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = File::create(file_path)?;
+        write!(file, "{}", self)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+// CSV table
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = match self.trials.first() {
+            Some(trial) => trial.parameters.values.iter().map(|(name, _)| name.clone()).collect(),
+            None => Vec::new(),
+        };
+        writeln!(f, "rank,{},selection_loss,selection_correct_rate,selection_macro_f1", names.join(","))?;
+        for (rank, trial) in self.trials.iter().enumerate() {
+            let values: Vec<String> = trial.parameters.values.iter().map(|(_, value)| format!("{}", value)).collect();
+            writeln!(
+                f,
+                "{},{},{},{},{}",
+                rank + 1,
+                values.join(","),
+                trial.result.loss,
+                trial.result.correct_rate,
+                trial.result.metrics.macro_f1()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Try all combinations of the space.
+// Trials learn with resource and are ranked on the selection data.
+pub fn grid_search<N, P>(
+    space: &SearchSpace,
+    resource: &LearningResource,
+    selection: &SelectionData,
+    make_network: N,
+    make_parameter: P,
+) -> SearchResult
+where
+    N: FnMut(&HyperParameters) -> NeuralNetwork,
+    P: FnMut(&HyperParameters) -> LearningParameter,
+{
+    return run_trials(space.grid(), resource, selection, make_network, make_parameter);
+}
+
+// Try trials_num configurations sampled from the space.
+// Trials learn with resource and are ranked on the selection data.
+pub fn random_search<N, P>(
+    space: &SearchSpace,
+    trials_num: usize,
+    seed: u64,
+    resource: &LearningResource,
+    selection: &SelectionData,
+    make_network: N,
+    make_parameter: P,
+) -> SearchResult
+where
+    N: FnMut(&HyperParameters) -> NeuralNetwork,
+    P: FnMut(&HyperParameters) -> LearningParameter,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let configurations = (0..trials_num).map(|_| space.sample(&mut rng)).collect();
+    return run_trials(configurations, resource, selection, make_network, make_parameter);
+}
+
+fn run_trials<N, P>(
+    configurations: Vec<HyperParameters>,
+    resource: &LearningResource,
+    selection: &SelectionData,
+    mut make_network: N,
+    mut make_parameter: P,
+) -> SearchResult
+where
+    N: FnMut(&HyperParameters) -> NeuralNetwork,
+    P: FnMut(&HyperParameters) -> LearningParameter,
+{
+    let mut trials = Vec::<TrialResult>::new();
+    for parameters in configurations {
+        let mut nn = make_network(&parameters);
+        let parameter = make_parameter(&parameters);
+        let batch_size = parameter.batch_size;
+        let history = nn.learn(parameter, resource.clone());
+        let result = nn.evaluate(batch_size, selection.data, selection.lbl_onehot);
+
+        trials.push(TrialResult {
+            parameters: parameters,
+            history: history,
+            result: result,
+        });
+    }
+
+    // NaN loss goes last
+    trials.sort_by(|a, b| {
+        let a_loss = if a.result.loss.is_nan() {f64::INFINITY} else {a.result.loss};
+        let b_loss = if b.result.loss.is_nan() {f64::INFINITY} else {b.result.loss};
+        a_loss.partial_cmp(&b_loss).unwrap()
+    });
+
+    return SearchResult {
+        trials: trials,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::layer::*;
+    use crate::deep_learning::optimizer::*;
+
+    #[test]
+    fn test_grid() {
+        let space = SearchSpace::new()
+            .add("learning_rate", ParameterSpace::Values(vec![0.1, 0.01]))
+            .add("batch_size", ParameterSpace::Values(vec![1.0, 2.0, 3.0]));
+
+        let grid = space.grid();
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[0].values, vec![("learning_rate".to_string(), 0.1), ("batch_size".to_string(), 1.0)]);
+        assert_eq!(grid[5].values, vec![("learning_rate".to_string(), 0.01), ("batch_size".to_string(), 3.0)]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let space = SearchSpace::new()
+            .add("learning_rate", ParameterSpace::LogUniform { min: 0.0001, max: 0.1 })
+            .add("batch_size", ParameterSpace::Values(vec![1.0, 2.0]));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let parameters = space.sample(&mut rng);
+            let learning_rate = parameters.get("learning_rate");
+            assert!(0.0001 <= learning_rate && learning_rate <= 0.1);
+            let batch_size = parameters.get("batch_size");
+            assert!(batch_size == 1.0 || batch_size == 2.0);
+        }
+        // Same seed, same samples
+        assert_eq!(space.sample(&mut StdRng::seed_from_u64(1)), space.sample(&mut StdRng::seed_from_u64(1)));
+    }
+
+    #[test]
+    fn test_grid_search() {
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+        let resource = LearningResource {
+            trn_data: data.clone(),
+            trn_lbl_onehot: lbl_onehot.clone(),
+            tst_data: data.clone(),
+            tst_lbl_onehot: lbl_onehot.clone(),
+            val_data: data.clone(),
+            val_lbl_onehot: lbl_onehot.clone(),
+        };
+        let selection_data = Array2::<f64>::from_shape_fn((4, 3), |(i, j)| (i * j) as f64 / 10.0);
+        let selection_lbl_onehot = Array2::<f64>::from_shape_fn((4, 2), |(i, j)| if i % 2 == j {0.0} else {1.0});
+        let space = SearchSpace::new()
+            .add("learning_rate", ParameterSpace::Values(vec![0.0, 0.5]))
+            .add("batch_size", ParameterSpace::Values(vec![2.0, 3.0]));
+
+        let result = grid_search(
+            &space,
+            &resource,
+            &SelectionData {
+                data: &selection_data,
+                lbl_onehot: &selection_lbl_onehot,
+            },
+            |parameters| {
+                let batch_size = parameters.get("batch_size") as usize;
+                let learning_rate = parameters.get("learning_rate");
+                let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+                let layers = Affine::new_random(
+                    layers,
+                    3,
+                    2,
+                    Sgd::new(learning_rate),
+                    Sgd::new(learning_rate)
+                );
+                let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+                let mut nn = NeuralNetwork::new(layers);
+                nn.set_quiet(true);
+                nn
+            },
+            |parameters| LearningParameter {
+                batch_size: parameters.get("batch_size") as usize,
+                iterations_num: 3,
+                sampling: MinibatchSampling::WithReplacement,
+                gradient_clipping: None,
+                scheduler: None,
+                seed: Some(0),
+                checkpoint: None,
+                validation: None,
+                callbacks: Vec::new(),
+                threads_num: 1,
+            },
+        );
+
+        assert_eq!(result.trials.len(), 4);
+        for i in 1..result.trials.len() {
+            assert!(result.trials[i - 1].result.loss <= result.trials[i].result.loss);
+        }
+        for trial in &result.trials {
+            assert_eq!(trial.result.sample_num, 4);
+        }
+        assert_eq!(result.best().unwrap().parameters, result.trials[0].parameters);
+
+        let table = format!("{}", result);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "rank,learning_rate,batch_size,selection_loss,selection_correct_rate,selection_macro_f1");
+        assert!(lines[1].starts_with("1,"));
+        assert!(lines[4].starts_with("4,"));
+    }
+}
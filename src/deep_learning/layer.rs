@@ -1,6 +1,9 @@
 mod network_layer;
 pub use network_layer::*;
 
+mod parameter;
+pub use parameter::*;
+
 mod direct_value;
 pub use direct_value::*;
 
@@ -22,6 +25,9 @@ pub use batch_norm::*;
 mod softmax_with_loss;
 pub use softmax_with_loss::*;
 
+mod distillation_with_loss;
+pub use distillation_with_loss::*;
+
 mod dropout;
 pub use dropout::*;
 
@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -6,6 +5,7 @@ use ndarray::prelude::{
 };
 
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 use crate::deep_learning::optimizer::*;
 use crate::deep_learning::common::*;
 use crate::deep_learning::*;
@@ -93,20 +93,21 @@ impl Affine {
     pub fn layer_label() -> &'static str {
         "affine"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
-        let x = neural_network::import_network_layer(lines);
-        let w = neural_network::import_network_layer(lines);
-        let b = neural_network::import_network_layer(lines);
+        let x = neural_network::import_network_layer(lines)?;
+        let w = neural_network::import_network_layer(lines)?;
+        let b = neural_network::import_network_layer(lines)?;
 
-        Affine {
-            x: x,
-            w: w,
-            b: b,
-            z: None,
-        }
+        Ok(
+            Affine {
+                x: x,
+                w: w,
+                b: b,
+                z: None,
+            }
+        )
     }
 }
 impl NetworkLayer for Affine {
@@ -162,8 +163,37 @@ impl NetworkLayer for Affine {
             self.w.weight_sum() + 
             self.b.weight_sum();
     }
+    fn set_trainable(&mut self, trainable: bool) {
+        self.w.set_trainable(trainable);
+        self.b.set_trainable(trainable);
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.set_trainable(trainable_num > 0);
+        self.x.freeze_except_top(trainable_num.saturating_sub(1));
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        let index = self.x.visit_parameters(visitor) + 1;
+        let prefix = layer_parameter_prefix(index, Self::layer_label());
+        for (name, parameter) in [("w", &mut self.w), ("b", &mut self.b)].iter_mut() {
+            let name = join_parameter_name(&prefix, name);
+            parameter.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| visitor.visit(&name, parameter));
+        }
+        index
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let output_shape = [last_output_shape(summaries)[0], parameter_shape(self.w.as_mut())[1]];
+        let (w_num, w_trainable_num) = count_parameters(self.w.as_mut());
+        let (b_num, b_trainable_num) = count_parameters(self.b.as_mut());
+        summaries.push(LayerSummary::new(
+            "Affine",
+            Self::layer_label(),
+            &output_shape,
+            (w_num + b_num, w_trainable_num + b_trainable_num)
+        ));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
         file.flush()?;
         self.x.export(file)?;
@@ -340,4 +370,54 @@ mod test {
         let mut affine = Affine::new(mock_x, mock_w, mock_b);
         affine.backward(dout);
     }
+
+    #[test]
+    fn test_affine_freeze_except_top() {
+        let mut mock_x = MockNetworkLayer::new();
+        mock_x.expect_freeze_except_top()
+            .times(1)
+            .with(eq(1))
+            .returning(|_| {})
+        ;
+        let mut mock_w = MockNetworkLayer::new();
+        mock_w.expect_set_trainable()
+            .times(1)
+            .with(eq(true))
+            .returning(|_| {})
+        ;
+        let mut mock_b = MockNetworkLayer::new();
+        mock_b.expect_set_trainable()
+            .times(1)
+            .with(eq(true))
+            .returning(|_| {})
+        ;
+
+        let mut affine = Affine::new(mock_x, mock_w, mock_b);
+        affine.freeze_except_top(2);
+    }
+
+    #[test]
+    fn test_affine_freeze_except_top_frozen() {
+        let mut mock_x = MockNetworkLayer::new();
+        mock_x.expect_freeze_except_top()
+            .times(1)
+            .with(eq(0))
+            .returning(|_| {})
+        ;
+        let mut mock_w = MockNetworkLayer::new();
+        mock_w.expect_set_trainable()
+            .times(1)
+            .with(eq(false))
+            .returning(|_| {})
+        ;
+        let mut mock_b = MockNetworkLayer::new();
+        mock_b.expect_set_trainable()
+            .times(1)
+            .with(eq(false))
+            .returning(|_| {})
+        ;
+
+        let mut affine = Affine::new(mock_x, mock_w, mock_b);
+        affine.freeze_except_top(0);
+    }
 }
@@ -1,11 +1,12 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufRead, BufReader, Lines};
 use ndarray::prelude::{
     Array2,
     Axis,
 };
 
+use crate::deep_learning::common::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 use crate::deep_learning::optimizer::*;
 use crate::deep_learning::graph_plotter::*;
 
@@ -14,7 +15,9 @@ use crate::deep_learning::graph_plotter::*;
 pub struct AffineDirectValue {
     value: Array2<f64>,
     optimizer: Box<dyn Optimizer>,
+    gradient: Option<Array2<f64>>,
     name: String,
+    trainable: bool,
 }
 impl AffineDirectValue {
     pub fn new<TO>(value: Array2<f64>, optimizer: TO)
@@ -24,7 +27,9 @@ impl AffineDirectValue {
         AffineDirectValue {
             value: value,
             optimizer: Box::new(optimizer),
+            gradient: None,
             name: "".to_string(),
+            trainable: true,
         }
     }
     pub fn new_from_len<TO>(row_len: usize, col_len: usize, optimizer: TO) -> AffineDirectValue
@@ -39,7 +44,9 @@ impl AffineDirectValue {
         AffineDirectValue {
             value: value,
             optimizer: Box::new(optimizer),
+            gradient: None,
             name: name,
+            trainable: true,
         }
     }
     pub fn new_from_len_with_name<TO>(row_len: usize, col_len: usize, optimizer: TO, name: String) -> AffineDirectValue
@@ -50,29 +57,45 @@ impl AffineDirectValue {
     pub fn layer_label() -> &'static str {
         "a_direct"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    // Label of the format with name and optimizer. Files labeled layer_label have only the value.
+    pub fn layer_label_v2() -> &'static str {
+        "a_direct_v2"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
-        // value shape
-        let shape_line = lines.next().unwrap();
-        let mut shape_line_split = shape_line.split(',');
-        let dim: (usize, usize) = (shape_line_split.next().unwrap().parse::<usize>().unwrap(), shape_line_split.next().unwrap().parse::<usize>().unwrap());
+        // name
+        let name = next_import_value(lines)?.to_string();
         // value
-        let mut value = Array2::<f64>::zeros(dim);
-        for row_i in 0..dim.0 {
-            let line = lines.next().unwrap();
-            let mut line_split = line.split(',');
-            for col_i in 0..dim.1 {
-                value[(row_i, col_i)] = line_split.next().unwrap().parse::<f64>().unwrap();
+        let value = import_value(lines)?;
+        // optimizer
+        let optimizer = import_optimizer(lines)?;
+
+        Ok(
+            AffineDirectValue {
+                value: value,
+                optimizer: optimizer,
+                gradient: None,
+                name: name,
+                trainable: true,
             }
-        }
+        )
+    }
+    // Format exported before names and optimizers were added
+    pub fn import_legacy<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        let value = import_value(lines)?;
 
-        AffineDirectValue {
-            value: value,
-            optimizer: Box::new(Sgd::new(0.1)),
-            name: "".to_string(),
-        }
+        Ok(
+            AffineDirectValue {
+                value: value,
+                optimizer: Box::new(Sgd::new(0.1)),
+                gradient: None,
+                name: "".to_string(),
+                trainable: true,
+            }
+        )
     }
 }
 impl NetworkLayer for AffineDirectValue {
@@ -80,8 +103,11 @@ impl NetworkLayer for AffineDirectValue {
         self.value.clone()
     }
     fn backward(&mut self, dout: Array2<f64>) {
-        let updated_value = self.optimizer.update(&self.value, &dout);
-        self.value.assign(&updated_value);
+        // Accumulate until step
+        match self.gradient.as_mut() {
+            Some(gradient) => *gradient += &dout,
+            None => self.gradient = Some(dout),
+        }
     }
     fn set_value(&mut self, value: &Array2<f64>) {
         if self.value.shape() != value.shape() {
@@ -108,10 +134,26 @@ impl NetworkLayer for AffineDirectValue {
     fn weight_sum(&self) -> f64 {
         panic!("AffineDirectValue::weight_sum is never weight_sum");
     }
+    fn set_trainable(&mut self, trainable: bool) {
+        self.trainable = trainable;
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.set_trainable(trainable_num > 0);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        // Parameter used as the input layer
+        visitor.visit(&layer_parameter_prefix(0, Self::layer_label()), self);
+        0
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        let trainable_num = if self.trainable {self.value.len()} else {0};
+        summaries.push(LayerSummary::new("AffineDirectValue", Self::layer_label(), self.value.shape(), (self.value.len(), trainable_num)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
-        writeln!(file, "{}", Self::layer_label())?;
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::layer_label_v2())?;
 
+        writeln!(file, "{}", self.name)?;
         writeln!(file, "{},{}", self.value.shape()[0], self.value.shape()[1])?;
         for row in self.value.axis_iter(Axis(0)) {
             for v in row {
@@ -119,11 +161,75 @@ impl NetworkLayer for AffineDirectValue {
             }
             writeln!(file, "")?;
         }
+        self.optimizer.export(file)?;
 
         file.flush()?;
         Ok(())
     }
 }
+impl Parameter for AffineDirectValue {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_value(&self) -> &Array2<f64> {
+        &self.value
+    }
+    fn get_value_mut(&mut self) -> &mut Array2<f64> {
+        &mut self.value
+    }
+    fn get_gradient(&self) -> Option<&Array2<f64>> {
+        self.gradient.as_ref()
+    }
+    fn get_gradient_mut(&mut self) -> Option<&mut Array2<f64>> {
+        self.gradient.as_mut()
+    }
+    fn zero_grad(&mut self) {
+        self.gradient = None;
+    }
+    fn step(&mut self) {
+        // Frozen value keeps its value
+        if !self.trainable {
+            return;
+        }
+        if let Some(gradient) = self.gradient.as_ref() {
+            let updated_value = self.optimizer.update(&self.value, gradient);
+            self.value.assign(&updated_value);
+        }
+    }
+    fn is_trainable(&self) -> bool {
+        self.trainable
+    }
+    fn get_learning_rate(&self) -> f64 {
+        self.optimizer.learning_rate()
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.optimizer.set_learning_rate(learning_rate);
+    }
+    fn get_optimizer(&self) -> &dyn Optimizer {
+        self.optimizer.as_ref()
+    }
+    fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>) {
+        self.optimizer = optimizer;
+    }
+}
+
+// Shape line and rows
+fn import_value<'a, T>(lines: &mut T) -> Result<Array2<f64>, Box<dyn std::error::Error>>
+    where T: Iterator<Item = &'a str>
+{
+    let shape_line = next_import_value(lines)?;
+    let mut shape_line_split = shape_line.split(',');
+    let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
+    let mut value = Array2::<f64>::zeros(dim);
+    for row_i in 0..dim.0 {
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        for col_i in 0..dim.1 {
+            value[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
+        }
+    }
+    return Ok(value);
+}
 
 #[cfg(test)]
 mod test {
@@ -151,8 +257,107 @@ mod test {
             ]
         );
         affine_direct_value.backward(expect_value.clone());
+        // Not updated until step
+        assert_eq!(affine_direct_value.value, Array2::<f64>::zeros((3, 5)));
 
+        affine_direct_value.step();
         assert_eq!(affine_direct_value.value, expect_value);
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_affine_direct_value_backward_accumulate() {
+        let mut affine_direct_value = AffineDirectValue::new_from_len(2, 3, Sgd::new(0.1));
+
+        let dout = arr2(&
+            [
+                [1f64, 2f64, 3f64],
+                [4f64, 5f64, 6f64],
+            ]
+        );
+        affine_direct_value.backward(dout.clone());
+        affine_direct_value.backward(dout.clone());
+        assert_eq!(affine_direct_value.get_gradient(), Some(&(&dout * 2f64)));
+
+        affine_direct_value.zero_grad();
+        assert_eq!(affine_direct_value.get_gradient(), None);
+    }
+
+    #[test]
+    fn test_affine_direct_value_backward_frozen() {
+        let mut mock_optimizer = MockOptimizer::new();
+        mock_optimizer.expect_update()
+            .times(0)
+        ;
+
+        let mut affine_direct_value = AffineDirectValue::new_from_len(2, 3, mock_optimizer);
+        affine_direct_value.set_trainable(false);
+
+        affine_direct_value.backward(arr2(&
+            [
+                [1f64, 2f64, 3f64],
+                [4f64, 5f64, 6f64],
+            ]
+        ));
+        affine_direct_value.step();
+
+        assert_eq!(affine_direct_value.is_trainable(), false);
+        assert_eq!(affine_direct_value.value, Array2::<f64>::zeros((2, 3)));
+    }
+
+    #[test]
+    fn test_affine_direct_value_export_import() {
+        let value = arr2(&
+            [
+                [1f64, 2f64, 3f64],
+                [4f64, 5f64, 6f64],
+            ]
+        );
+        let dout = arr2(&
+            [
+                [0.1f64, -0.2f64, 0.3f64],
+                [0.4f64, 0.5f64, -0.6f64],
+            ]
+        );
+        let mut affine_direct_value = AffineDirectValue::new_with_name(value.clone(), Adam::new(0.01, 0.9, 0.999), "layer1_weight".to_string());
+        affine_direct_value.backward(dout.clone());
+        affine_direct_value.step();
+
+        let mut buffer = Vec::<u8>::new();
+        affine_direct_value.export(&mut buffer).unwrap();
+
+        let data = String::from_utf8(buffer).unwrap();
+        let mut lines = data.lines();
+        assert_eq!(lines.next().unwrap(), AffineDirectValue::layer_label_v2());
+        let mut imported = AffineDirectValue::import(&mut lines).unwrap();
+
+        assert_eq!(imported.get_name(), "layer1_weight");
+        assert_eq!(imported.get_value(), affine_direct_value.get_value());
+        assert_eq!(imported.get_learning_rate(), 0.01);
+
+        // Continue updating with the restored optimizer state
+        for parameter in [&mut affine_direct_value, &mut imported].iter_mut() {
+            parameter.zero_grad();
+            parameter.backward(dout.clone());
+            parameter.step();
+        }
+        assert_eq!(imported.get_value(), affine_direct_value.get_value());
+    }
+
+    #[test]
+    fn test_affine_direct_value_import_legacy() {
+        let data = "a_direct\n2,3\n1,2,3,\n4,5,6,\nrelu\n";
+        let mut lines = data.lines();
+
+        let mut layer = crate::deep_learning::neural_network::import_network_layer(&mut lines).unwrap();
+
+        // Next layer follows the value
+        assert_eq!(lines.next(), Some("relu"));
+        assert_eq!(layer.forward(false), arr2(&
+            [
+                [1f64, 2f64, 3f64],
+                [4f64, 5f64, 6f64],
+            ]
+        ));
+    }
+}
@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -7,12 +6,15 @@ use ndarray::prelude::{
 
 use crate::deep_learning::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 use crate::deep_learning::optimizer::*;
 use crate::deep_learning::common::*;
 
 pub struct NetworkBatchNormValueLayer {
     value: Array2<f64>,
     optimizer: Box<dyn Optimizer>,
+    gradient: Option<Array2<f64>>,
+    trainable: bool,
 }
 impl NetworkBatchNormValueLayer {
     pub fn new<TO>(value: Array2<f64>, optimizer: TO) -> Self
@@ -21,6 +23,8 @@ impl NetworkBatchNormValueLayer {
         NetworkBatchNormValueLayer {
             value: value,
             optimizer: Box::new(optimizer),
+            gradient: None,
+            trainable: true,
         }
     }
     pub fn new_from_len<TO>(row_len: usize, col_len: usize, optimizer: TO) -> AffineDirectValue
@@ -28,14 +32,48 @@ impl NetworkBatchNormValueLayer {
     {
         return AffineDirectValue::new(Array2::<f64>::zeros((row_len, col_len)), optimizer);
     }
+    pub fn layer_label() -> &'static str {
+        "bn_value"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // value shape
+        let shape_line = next_import_value(lines)?;
+        let mut shape_line_split = shape_line.split(',');
+        let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
+        // value
+        let mut value = Array2::<f64>::zeros(dim);
+        for row_i in 0..dim.0 {
+            let line = next_import_value(lines)?;
+            let mut line_split = line.split(',');
+            for col_i in 0..dim.1 {
+                value[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
+            }
+        }
+        // optimizer
+        let optimizer = import_optimizer(lines)?;
+
+        Ok(
+            NetworkBatchNormValueLayer {
+                value: value,
+                optimizer: optimizer,
+                gradient: None,
+                trainable: true,
+            }
+        )
+    }
 }
 impl NetworkLayer for NetworkBatchNormValueLayer {
     fn forward(&mut self, _is_learning: bool) -> Array2<f64> {
         self.value.clone()
     }
     fn backward(&mut self, dout: Array2<f64>) {
-        let updated_value = self.optimizer.update(&self.value, &dout);
-        self.value.assign(&updated_value);
+        // Accumulate until step
+        match self.gradient.as_mut() {
+            Some(gradient) => *gradient += &dout,
+            None => self.gradient = Some(dout),
+        }
     }
     fn set_value(&mut self, value: &Array2<f64>) {
         if self.value.shape() != value.shape() {
@@ -58,6 +96,82 @@ impl NetworkLayer for NetworkBatchNormValueLayer {
     fn weight_sum(&self) -> f64 {
         return 0f64;
     }
+    fn set_trainable(&mut self, trainable: bool) {
+        self.trainable = trainable;
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.set_trainable(trainable_num > 0);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        // Parameter used as the input layer
+        visitor.visit(&layer_parameter_prefix(0, Self::layer_label()), self);
+        0
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        let trainable_num = if self.trainable {self.value.len()} else {0};
+        summaries.push(LayerSummary::new("NetworkBatchNormValueLayer", Self::layer_label(), self.value.shape(), (self.value.len(), trainable_num)));
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::layer_label())?;
+
+        writeln!(file, "{},{}", self.value.shape()[0], self.value.shape()[1])?;
+        for row in self.value.axis_iter(Axis(0)) {
+            for v in row {
+                write!(file, "{},", v)?;
+            }
+            writeln!(file, "")?;
+        }
+        self.optimizer.export(file)?;
+
+        file.flush()?;
+        Ok(())
+    }
+}
+impl Parameter for NetworkBatchNormValueLayer {
+    fn get_name(&self) -> &str {
+        ""
+    }
+    fn get_value(&self) -> &Array2<f64> {
+        &self.value
+    }
+    fn get_value_mut(&mut self) -> &mut Array2<f64> {
+        &mut self.value
+    }
+    fn get_gradient(&self) -> Option<&Array2<f64>> {
+        self.gradient.as_ref()
+    }
+    fn get_gradient_mut(&mut self) -> Option<&mut Array2<f64>> {
+        self.gradient.as_mut()
+    }
+    fn zero_grad(&mut self) {
+        self.gradient = None;
+    }
+    fn step(&mut self) {
+        // Frozen value keeps its value
+        if !self.trainable {
+            return;
+        }
+        if let Some(gradient) = self.gradient.as_ref() {
+            let updated_value = self.optimizer.update(&self.value, gradient);
+            self.value.assign(&updated_value);
+        }
+    }
+    fn is_trainable(&self) -> bool {
+        self.trainable
+    }
+    fn get_learning_rate(&self) -> f64 {
+        self.optimizer.learning_rate()
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.optimizer.set_learning_rate(learning_rate);
+    }
+    fn get_optimizer(&self) -> &dyn Optimizer {
+        self.optimizer.as_ref()
+    }
+    fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>) {
+        self.optimizer = optimizer;
+    }
 }
 
 // Batch normalization
@@ -90,23 +204,24 @@ impl BatchNorm {
     pub fn layer_label() -> &'static str {
         "batchnorm"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
-        let x = neural_network::import_network_layer(lines);
-        let w = neural_network::import_network_layer(lines);
-        let b = neural_network::import_network_layer(lines);
-
-        BatchNorm {
-            x: x,
-            y: None, 
-            w: w,
-            b: b,
-            normalized: None,
-            distribute: None,
-            average: None,
-        }
+        let x = neural_network::import_network_layer(lines)?;
+        let w = neural_network::import_network_layer(lines)?;
+        let b = neural_network::import_network_layer(lines)?;
+
+        Ok(
+            BatchNorm {
+                x: x,
+                y: None, 
+                w: w,
+                b: b,
+                normalized: None,
+                distribute: None,
+                average: None,
+            }
+        )
     }
 }
 impl NetworkLayer for BatchNorm {
@@ -182,13 +297,13 @@ impl NetworkLayer for BatchNorm {
         let d7 = d8 * (-1.0 / (distribute + 10f64.powi(-6)));
 
         // d6 sqrt(x)
-        let d6 = d7 / (2.0 * sqrt_arr2(&(distribute + 10f64.powi(-6).sqrt())));
+        let d6 = d7 / (2.0 * sqrt_arr2(&(distribute + 10f64.powi(-6))));
 
         // d5 avg
         let d5 = backword_average(&d6, x.shape()[0]);
 
         // d4 ^2
-        let d4 = d5 * (x.clone() - average);
+        let d4 = d5 * 2.0 * (x.clone() - average);
         
         //d3a -
         let d3a = d9a + d4;
@@ -205,6 +320,8 @@ impl NetworkLayer for BatchNorm {
         let dx = &d3a + d1;
 
         self.x.backward(dx);
+        self.w.backward(dw);
+        self.b.backward(db);
     }
     fn set_value(&mut self, value: &Array2<f64>) {
         self.x.set_value(value);
@@ -232,8 +349,37 @@ impl NetworkLayer for BatchNorm {
             self.w.weight_sum() +
             self.b.weight_sum();
     }
+    fn set_trainable(&mut self, trainable: bool) {
+        self.w.set_trainable(trainable);
+        self.b.set_trainable(trainable);
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.set_trainable(trainable_num > 0);
+        self.x.freeze_except_top(trainable_num.saturating_sub(1));
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        let index = self.x.visit_parameters(visitor) + 1;
+        let prefix = layer_parameter_prefix(index, Self::layer_label());
+        for (name, parameter) in [("w", &mut self.w), ("b", &mut self.b)].iter_mut() {
+            let name = join_parameter_name(&prefix, name);
+            parameter.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| visitor.visit(&name, parameter));
+        }
+        index
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let output_shape = last_output_shape(summaries);
+        let (w_num, w_trainable_num) = count_parameters(self.w.as_mut());
+        let (b_num, b_trainable_num) = count_parameters(self.b.as_mut());
+        summaries.push(LayerSummary::new(
+            "BatchNorm",
+            Self::layer_label(),
+            &output_shape,
+            (w_num + b_num, w_trainable_num + b_trainable_num)
+        ));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
         file.flush()?;
         self.x.export(file)?;
@@ -304,6 +450,7 @@ mod test {
         arr2,
     };
     use rand::{Rng};
+    use crate::deep_learning::gradient_check::*;
 
     #[test]
     fn test_batch_norm_forward() {
@@ -424,4 +571,72 @@ mod test {
             ]
         ));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_batch_norm_gradient_check() {
+        let input = Array2::from_shape_vec((4, 3), norm_random_vec(4 * 3)).unwrap();
+        let dout = Array2::from_shape_vec((4, 3), norm_random_vec(4 * 3)).unwrap();
+
+        let results = check_gradient(
+            |x| BatchNorm::new(
+                x,
+                NetworkBatchNormValueLayer::new(
+                    Array2::from_shape_vec((1, 3), norm_random_vec(3)).unwrap(),
+                    Sgd::new(0.01)
+                ),
+                NetworkBatchNormValueLayer::new(
+                    Array2::from_shape_vec((1, 3), norm_random_vec(3)).unwrap(),
+                    Sgd::new(0.01)
+                ),
+            ),
+            input,
+            &dout
+        );
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["input", "1.batchnorm/w", "1.batchnorm/b"]);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
+
+    #[test]
+    fn test_batch_norm_export_import() {
+        let input = Array2::from_shape_vec((4, 3), norm_random_vec(4 * 3)).unwrap();
+        let dout = Array2::from_shape_vec((4, 3), norm_random_vec(4 * 3)).unwrap();
+
+        let mut batch_norm = BatchNorm::new(
+            DirectValue::new(input.clone()),
+            NetworkBatchNormValueLayer::new(
+                Array2::from_shape_vec((1, 3), norm_random_vec(3)).unwrap(),
+                Adam::new(0.01, 0.9, 0.999)
+            ),
+            NetworkBatchNormValueLayer::new(
+                Array2::from_shape_vec((1, 3), norm_random_vec(3)).unwrap(),
+                Momentum::new(0.01, 0.9)
+            ),
+        );
+        batch_norm.forward(true);
+        batch_norm.backward(dout.clone());
+        batch_norm.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| parameter.step());
+
+        let mut buffer = Vec::<u8>::new();
+        batch_norm.export(&mut buffer).unwrap();
+
+        let data = String::from_utf8(buffer).unwrap();
+        let mut lines = data.lines();
+        let mut imported = neural_network::import_network_layer(&mut lines).unwrap();
+        assert_eq!(lines.next(), None);
+
+        // Continue updating with the restored optimizer state
+        for layer in [&mut batch_norm as &mut dyn NetworkLayer, imported.as_mut()].iter_mut() {
+            layer.clean();
+            layer.forward(true);
+            layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| parameter.zero_grad());
+            layer.backward(dout.clone());
+            layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| parameter.step());
+            layer.clean();
+        }
+        assert_eq!(imported.forward(false), batch_norm.forward(false));
+    }
+}
@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::{
     s,
@@ -10,6 +9,7 @@ use ndarray::{
 
 use crate::deep_learning::*;
 use super::super::super::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 use super::super::super::deep_learning::optimizer::*;
 use super::super::super::deep_learning::common::*;
 
@@ -104,67 +104,75 @@ impl Convolution {
             pad
         );
     }
+    // x_shape and y_shape for the batch size of the current input
+    fn batch_shapes(&self, batch_num: usize) -> ((usize, usize, usize, usize), (usize, usize, usize, usize)) {
+        (
+            (batch_num, self.x_shape.1, self.x_shape.2, self.x_shape.3),
+            (batch_num, self.y_shape.1, self.y_shape.2, self.y_shape.3),
+        )
+    }
     pub fn layer_label() -> &'static str {
         "conv"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
 
         // x_shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
         let x_shape: (usize, usize, usize, usize) = (
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
         );
 
         // y_shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
         let y_shape: (usize, usize, usize, usize) = (
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
         );
 
         // filter_shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
         let filter_shape: (usize, usize, usize, usize) = (
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
         );
 
         // stride
-        let value_line = lines.next().unwrap();
-        let stride = value_line.parse::<usize>().unwrap();
+        let value_line = next_import_value(lines)?;
+        let stride = value_line.parse::<usize>()?;
 
         // pad
-        let value_line = lines.next().unwrap();
-        let pad = value_line.parse::<usize>().unwrap();
-
-        let x = neural_network::import_network_layer(lines);
-        let filter = neural_network::import_network_layer(lines);
-        let bias = neural_network::import_network_layer(lines);
-
-        Convolution {
-            x: x,
-            y: None,
-            filter: filter,
-            bias: bias,
-            x_shape: x_shape,
-            y_shape: y_shape,
-            filter_shape: filter_shape,
-            stride: stride,
-            pad: pad,
-        }
+        let value_line = next_import_value(lines)?;
+        let pad = value_line.parse::<usize>()?;
+
+        let x = neural_network::import_network_layer(lines)?;
+        let filter = neural_network::import_network_layer(lines)?;
+        let bias = neural_network::import_network_layer(lines)?;
+
+        Ok(
+            Convolution {
+                x: x,
+                y: None,
+                filter: filter,
+                bias: bias,
+                x_shape: x_shape,
+                y_shape: y_shape,
+                filter_shape: filter_shape,
+                stride: stride,
+                pad: pad,
+            }
+        )
     }
 }
 
@@ -176,18 +184,18 @@ impl NetworkLayer for Convolution {
             let bias_2d = self.bias.forward(is_learning);           
 
             // Reshape to 4d
-            let x_4d = x_2d.to_shared().reshape(self.x_shape).to_owned();
+            let (x_shape, y_shape) = self.batch_shapes(x_2d.shape()[0]);
+            let x_4d = x_2d.to_shared().reshape(x_shape).to_owned();
             
-            let (batch_num, _channel_num, _data_h, _data_w) = self.x_shape;
             let (filter_num, _channel_num, filter_h, filter_w) = self.filter_shape;
             let col_x_2d = im2col(&x_4d.to_owned(), filter_h, filter_w, self.stride, self.pad);
 
             let col_y = col_x_2d.dot(&filter_2d.t()) + bias_2d.t();
 
-            let mut col_y_3d = col_y.to_shared().reshape((self.y_shape.0, self.y_shape.2*self.y_shape.3, self.y_shape.1));
+            let mut col_y_3d = col_y.to_shared().reshape((y_shape.0, y_shape.2*y_shape.3, y_shape.1));
             col_y_3d.swap_axes(1, 2);
 
-            let y = col_y_3d.to_shared().reshape((self.y_shape.0, self.y_shape.1*self.y_shape.2*self.y_shape.3)).to_owned();
+            let y = col_y_3d.to_shared().reshape((y_shape.0, y_shape.1*y_shape.2*y_shape.3)).to_owned();
 
             // println!("y: {:?}", y);
 
@@ -199,8 +207,9 @@ impl NetworkLayer for Convolution {
         // println!("conv backward");
         self.forward(true);
 
-        let (batch_num, channel_num, x_h, x_w) = self.x_shape;
-        let (batch_num, _, step_h, step_w) = self.y_shape;
+        let (x_shape, y_shape) = self.batch_shapes(dout.shape()[0]);
+        let (batch_num, channel_num, x_h, x_w) = x_shape;
+        let (batch_num, _, step_h, step_w) = y_shape;
         let (filter_num, _, filter_h, filter_w) = self.filter_shape;
 
         // dout(B, FN SH SW) -> dout(FN, B SH SW)
@@ -219,7 +228,7 @@ impl NetworkLayer for Convolution {
 
         // df = dout CX
         let x_2d = self.x.forward(true);
-        let x_4d = x_2d.to_shared().reshape(self.x_shape).to_owned();
+        let x_4d = x_2d.to_shared().reshape(x_shape).to_owned();
         let col_x_2d = im2col(&x_4d.to_owned(), filter_h, filter_w, self.stride, self.pad);
         // let mut df = dout_2d.dot(&col_x_2d);
         // println!("col_x_2d_shape: {:?}", col_x_2d.shape());
@@ -230,7 +239,7 @@ impl NetworkLayer for Convolution {
 
         // dx = col2im((F.t dout).t)
         let filter_2d = self.filter.forward(true);
-        let dx_4d = col2im(&(dout_2d.dot(&filter_2d)).to_owned(), self.x_shape, self.filter_shape, self.stride, self.pad);
+        let dx_4d = col2im(&(dout_2d.dot(&filter_2d)).to_owned(), x_shape, self.filter_shape, self.stride, self.pad);
         let dx = dx_4d.to_shared().reshape((batch_num, channel_num*x_h*x_w)).to_owned();
         // println!("dx: {:?}", dx);
         self.x.backward(dx);
@@ -257,8 +266,37 @@ impl NetworkLayer for Convolution {
     fn weight_sum(&self) -> f64 {
         return self.weight_sum();
     }
+    fn set_trainable(&mut self, trainable: bool) {
+        self.filter.set_trainable(trainable);
+        self.bias.set_trainable(trainable);
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.set_trainable(trainable_num > 0);
+        self.x.freeze_except_top(trainable_num.saturating_sub(1));
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        let index = self.x.visit_parameters(visitor) + 1;
+        let prefix = layer_parameter_prefix(index, Self::layer_label());
+        for (name, parameter) in [("filter", &mut self.filter), ("bias", &mut self.bias)].iter_mut() {
+            let name = join_parameter_name(&prefix, name);
+            parameter.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| visitor.visit(&name, parameter));
+        }
+        index
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let (_, (batch_num, filter_num, step_h, step_w)) = self.batch_shapes(last_output_shape(summaries)[0]);
+        let (w_num, w_trainable_num) = count_parameters(self.filter.as_mut());
+        let (b_num, b_trainable_num) = count_parameters(self.bias.as_mut());
+        summaries.push(LayerSummary::new(
+            "Convolution",
+            Self::layer_label(),
+            &[batch_num, filter_num * step_h * step_w],
+            (w_num + b_num, w_trainable_num + b_trainable_num)
+        ));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
 
         writeln!(file, "{},{},{},{}", self.x_shape.0, self.x_shape.1, self.x_shape.2, self.x_shape.3)?;
@@ -407,6 +445,7 @@ mod test {
     };
 
     use crate::deep_learning::statistics::*;
+    use crate::deep_learning::gradient_check::*;
 
     #[test]
     fn test_convolution_new_random() {
@@ -1351,4 +1390,24 @@ mod test {
 
         return img;
     }
+
+    #[test]
+    fn test_convolution_gradient_check() {
+        // B:2, C:2 H:5 W:5
+        let input = Array2::from_shape_vec((2, 2*5*5), norm_random_vec(2*2*5*5)).unwrap();
+        // B:2, FN:3 H:3 W:3
+        let dout = Array2::from_shape_vec((2, 3*3*3), norm_random_vec(2*3*3*3)).unwrap();
+
+        let results = check_gradient(
+            |x| Convolution::new_random(x, Sgd::new(0.01), Sgd::new(0.01), 2, 2, 3, 3, 3, 5, 5, 2, 1),
+            input,
+            &dout
+        );
+
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["input", "1.conv/filter", "1.conv/bias"]);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
 }
@@ -1,6 +1,5 @@
 
 #[cfg (not (target_family = "wasm"))]
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Axis,
@@ -8,7 +7,9 @@ use ndarray::prelude::{
 };
 
 use crate::deep_learning::*;
+use crate::deep_learning::common::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 
 pub struct DirectValue {
     value: Array2<f64>,
@@ -25,27 +26,28 @@ impl DirectValue {
     pub fn layer_label() -> &'static str {
         "direct"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
         // value shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
-        let dim: (usize, usize) = (shape_line_split.next().unwrap().parse::<usize>().unwrap(), shape_line_split.next().unwrap().parse::<usize>().unwrap());
+        let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
         // value
         let mut value = Array2::<f64>::zeros(dim);
         for row_i in 0..dim.0 {
-            let line = lines.next().unwrap();
+            let line = next_import_value(lines)?;
             let mut line_split = line.split(',');
             for col_i in 0..dim.1 {
-                value[(row_i, col_i)] = line_split.next().unwrap().parse::<f64>().unwrap();
+                value[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
             }
         }
 
-        DirectValue {
-            value: value,
-        }
+        Ok(
+            DirectValue {
+                value: value,
+            }
+        )
     }
 }
 impl NetworkLayer for DirectValue {
@@ -56,10 +58,11 @@ impl NetworkLayer for DirectValue {
         // Nothinf to do
     }
     fn set_value(&mut self, value: &Array2<f64>) {
-        if self.value.shape() != value.shape() {
+        // Batch size (row count) can change. e.g. the last partial batch of an epoch
+        if self.value.shape()[1] != value.shape()[1] {
             panic!("Different shape. self.value: {:?} value:{:?}", self.value.shape(), value.shape());
         }
-        self.value.assign(value);
+        self.value = value.clone();
     }
     fn set_lbl(&mut self, _value: &Array2<f64>) {
         // Nothing to do
@@ -76,8 +79,18 @@ impl NetworkLayer for DirectValue {
     fn weight_sum(&self) -> f64 {
         return 0f64;
     }
+    fn freeze_except_top(&mut self, _trainable_num: usize) {
+        // Nothing to do
+    }
+    fn visit_parameters(&mut self, _visitor: &mut dyn ParameterVisitor) -> usize {
+        // Input layer
+        0
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        summaries.push(LayerSummary::new("DirectValue", Self::layer_label(), self.value.shape(), (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
 
         writeln!(file, "{},{}", self.value.shape()[0], self.value.shape()[1])?;
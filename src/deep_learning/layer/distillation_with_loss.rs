@@ -0,0 +1,336 @@
+use std::f64::consts::E;
+#[cfg (not (target_family = "wasm"))]
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+use ndarray::Axis;
+
+use crate::deep_learning::*;
+use crate::deep_learning::common::*;
+use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
+use crate::deep_learning::neural_network::NeuralNetwork;
+
+// Knowledge distillation loss.
+// alpha * CrossEntropy(softmax(x), t) + (1 - alpha) * T^2 * KL(softmax(teacher / T) || softmax(x / T))
+// Teacher receives the same input as the student and is not trained.
+pub struct DistillationWithLoss {
+    x: Box<dyn NetworkLayer>,
+    teacher: Box<dyn NetworkLayer>,
+    t: Array2<f64>,
+    temperature: f64,
+    alpha: f64,
+    soft_target: Option<Array2<f64>>,
+    z: Option<Array2<f64>>,
+}
+impl DistillationWithLoss {
+    pub fn new<TX>(x: TX, teacher: NeuralNetwork, t: Array2<f64>, temperature: f64, alpha: f64) -> DistillationWithLoss
+    where TX: NetworkLayer + 'static
+    {
+        if temperature <= 0.0 {
+            panic!("temperature must be positive. temperature: {}", temperature);
+        }
+        DistillationWithLoss {
+            x: Box::new(x),
+            teacher: teacher.get_layers(),
+            t: t,
+            temperature: temperature,
+            alpha: alpha,
+            soft_target: None,
+            z: None,
+        }
+    }
+    pub fn get_x(&self) -> &Box<dyn NetworkLayer> {&self.x}
+    pub fn get_t(&self) -> &Array2<f64> {&self.t}
+    pub fn get_temperature(&self) -> f64 {self.temperature}
+    pub fn get_alpha(&self) -> f64 {self.alpha}
+    pub fn layer_label() -> &'static str {
+        "distillation"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // temperature, alpha
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let temperature = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let alpha = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // t shape
+        let shape_line = next_import_value(lines)?;
+        let mut shape_line_split = shape_line.split(',');
+        let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
+        // t
+        let mut t = Array2::<f64>::zeros(dim);
+        for row_i in 0..dim.0 {
+            let line = next_import_value(lines)?;
+            let mut line_split = line.split(',');
+            for col_i in 0..dim.1 {
+                t[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
+            }
+        }
+
+        let x = neural_network::import_network_layer(lines)?;
+        let teacher = neural_network::import_network_layer(lines)?;
+
+        Ok(
+            DistillationWithLoss {
+                x: x,
+                teacher: teacher,
+                t: t,
+                temperature: temperature,
+                alpha: alpha,
+                soft_target: None,
+                z: None,
+            }
+        )
+    }
+    // softmax(teacher / T)
+    fn soft_target(&mut self) -> Array2<f64> {
+        if self.soft_target.is_none() {
+            let teacher_logits = self.teacher.forward_skip_loss(false);
+            self.soft_target = Some(softmax(&(teacher_logits / self.temperature)));
+        }
+        self.soft_target.clone().unwrap()
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export_t(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{},{}", self.t.shape()[0], self.t.shape()[1])?;
+        for row in self.t.axis_iter(Axis(0)) {
+            for v in row {
+                write!(file, "{},", v)?;
+            }
+            writeln!(file, "")?;
+        }
+        Ok(())
+    }
+}
+impl NetworkLayer for DistillationWithLoss {
+    fn forward(&mut self, is_learning: bool) -> Array2<f64> {
+        if self.z.is_none() {
+            let x = self.x.forward(is_learning);
+            let soft_target = self.soft_target();
+
+            let hard_loss = crosss_entropy_error(&softmax(&x), &self.t);
+            let soft_loss = kl_divergence(&soft_target, &softmax(&(x / self.temperature)));
+
+            let z = hard_loss * self.alpha + soft_loss * (1.0 - self.alpha) * self.temperature.powi(2);
+            self.z = Some(z);
+        }
+        self.z.clone().unwrap()
+    }
+    fn forward_skip_loss(&mut self, is_learning: bool) -> Array2<f64> {
+        self.x.forward(is_learning)
+    }
+    fn backward(&mut self, dout: Array2<f64>) {
+        let x = self.x.forward(true);
+        let soft_target = self.soft_target();
+
+        // d(T^2 * KL)/dx = T * (softmax(x / T) - soft_target)
+        let hard_dx = (softmax(&x) - &self.t) * self.alpha;
+        let soft_dx = (softmax(&(&x / self.temperature)) - soft_target) * (1.0 - self.alpha) * self.temperature;
+        let dx = dout * (hard_dx + soft_dx);
+
+        self.x.backward(dx);
+    }
+    fn set_value(&mut self, value: &Array2<f64>) {
+        self.x.set_value(value);
+        self.teacher.set_value(value);
+        self.clean();
+    }
+    fn set_lbl(&mut self, value: &Array2<f64>) {
+        // Batch size (row count) can change
+        if self.t.shape()[1] != value.shape()[1] {
+            panic!("Different shape. self.t: {:?} value:{:?}", self.t.shape(), value.shape());
+        }
+        self.t = value.clone();
+        self.x.set_lbl(value);
+        self.clean();
+    }
+    fn clean(&mut self) {
+        self.soft_target = None;
+        self.z = None;
+    }
+    fn plot(&self){
+        self.x.plot();
+    }
+    fn weight_squared_sum(&self) -> f64 {
+        return self.x.weight_squared_sum();
+    }
+    fn weight_sum(&self) -> f64 {
+        return self.x.weight_sum();
+    }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        // Teacher parameters are not trained
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        // Loss of each row
+        let output_shape = [last_output_shape(summaries)[0], 1];
+        summaries.push(LayerSummary::new("DistillationWithLoss", Self::layer_label(), &output_shape, (0, 0)));
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::layer_label())?;
+        writeln!(file, "{},{}", self.temperature, self.alpha)?;
+        self.export_t(file)?;
+
+        file.flush()?;
+        self.x.export(file)?;
+        self.teacher.export(file)?;
+        Ok(())
+    }
+    // Student only, as SoftmaxWithLoss
+    #[cfg (not (target_family = "wasm"))]
+    fn export_inference(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", SoftmaxWithLoss::layer_label())?;
+        self.export_t(file)?;
+
+        file.flush()?;
+        self.x.export(file)?;
+        Ok(())
+    }
+}
+
+// KL(p || q) of each row
+fn kl_divergence(p: &Array2<f64>, q: &Array2<f64>) -> Array2<f64> {
+    if p.shape() != q.shape() {
+        panic!("Different shape. p:{:?} q:{:?}", p.shape(), q.shape());
+    }
+
+    let mut z = Array2::<f64>::zeros([p.shape()[0], 1]);
+    for row_i in 0..p.shape()[0] {
+        let mut sum = 0f64;
+        for col_i in 0..p.shape()[1] {
+            // INFINITY measure( 0 < p, q)
+            let p_v = p[(row_i, col_i)] + 0.00000001;
+            let q_v = q[(row_i, col_i)] + 0.00000001;
+            sum += p_v * (p_v.log(E) - q_v.log(E));
+        }
+        z[(row_i, 0)] = sum;
+    }
+    return z;
+}
+
+#[cfg(test)]
+mod test_distillation_with_loss_mod {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    use crate::deep_learning::gradient_check::*;
+    use crate::deep_learning::optimizer::*;
+
+    fn teacher(logits: Array2<f64>) -> NeuralNetwork {
+        NeuralNetwork::new(DirectValue::new(logits))
+    }
+
+    #[test]
+    fn test_kl_divergence() {
+        let p = arr2(&[[0.5, 0.5], [0.9, 0.1]]);
+        let q = arr2(&[[0.5, 0.5], [0.5, 0.5]]);
+
+        let kl = kl_divergence(&p, &q);
+
+        assert_eq!(kl.shape(), [2, 1]);
+        assert_eq!(round_digit(kl[(0, 0)], -6), 0.0);
+        assert_eq!(round_digit(kl[(1, 0)], -6), round_digit(0.9 * (0.9f64 / 0.5).ln() + 0.1 * (0.1f64 / 0.5).ln(), -6));
+    }
+
+    #[test]
+    fn test_forward() {
+        let x = arr2(&[[1.0, 2.0, 0.5]]);
+        let teacher_logits = arr2(&[[0.0, 3.0, 1.0]]);
+        let t = arr2(&[[0.0, 1.0, 0.0]]);
+
+        // alpha 1 is the same as SoftmaxWithLoss
+        let mut distillation = DistillationWithLoss::new(DirectValue::new(x.clone()), teacher(teacher_logits.clone()), t.clone(), 2.0, 1.0);
+        let mut softmax_with_loss = SoftmaxWithLoss::new(DirectValue::new(x.clone()), t.clone());
+        assert_eq!(round_digit_arr2(&distillation.forward(false), -6), round_digit_arr2(&softmax_with_loss.forward(false), -6));
+
+        // Same as teacher, no soft loss
+        let mut distillation = DistillationWithLoss::new(DirectValue::new(teacher_logits.clone()), teacher(teacher_logits.clone()), t.clone(), 2.0, 0.0);
+        assert_eq!(round_digit(distillation.forward(false)[(0, 0)], -6), 0.0);
+    }
+
+    #[test]
+    fn test_set_value() {
+        // Teacher outputs its input
+        let mut distillation = DistillationWithLoss::new(
+            DirectValue::new(Array2::<f64>::zeros((1, 2))),
+            teacher(Array2::<f64>::zeros((1, 2))),
+            arr2(&[[1.0, 0.0]]),
+            1.0,
+            0.0
+        );
+        let input = arr2(&[[2.0, 0.0], [0.0, 2.0]]);
+        distillation.set_value(&input);
+        distillation.set_lbl(&arr2(&[[1.0, 0.0], [0.0, 1.0]]));
+
+        // Student and teacher see the same input
+        let loss = distillation.forward(false);
+        assert_eq!(loss.shape(), [2, 1]);
+        assert_eq!(round_digit(loss.sum(), -6), 0.0);
+    }
+
+    #[test]
+    fn test_gradient_check() {
+        let input = Array2::from_shape_vec((3, 4), norm_random_vec(3 * 4)).unwrap();
+        let teacher_logits = Array2::from_shape_vec((3, 4), norm_random_vec(3 * 4)).unwrap();
+        let t = arr2(&
+            [
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 0.0],
+            ]
+        );
+        let dout = Array2::<f64>::ones((3, 1));
+
+        let results = check_gradient(|x| DistillationWithLoss::new(x, teacher(teacher_logits), t, 3.0, 0.3), input, &dout);
+
+        assert_eq!(results.len(), 1);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
+
+    #[test]
+    fn test_export_import() {
+        let student = DirectValue::new(Array2::<f64>::zeros((2, 3)));
+        let student = Affine::new_random(student, 3, 2, Sgd::new(0.1), Sgd::new(0.1));
+        let teacher_layers = DirectValue::new(Array2::<f64>::zeros((2, 3)));
+        let teacher_layers = Affine::new_random(teacher_layers, 3, 2, Sgd::new(0.1), Sgd::new(0.1));
+        let teacher_layers = SoftmaxWithLoss::new(teacher_layers, Array2::<f64>::zeros((2, 2)));
+        let mut nn = NeuralNetwork::new(DistillationWithLoss::new(
+            student,
+            NeuralNetwork::new(teacher_layers),
+            Array2::<f64>::zeros((2, 2)),
+            2.0,
+            0.5
+        ));
+        let data = arr2(&[[0.1, 0.2, 0.3], [0.3, 0.2, 0.1]]);
+        let lbl_onehot = arr2(&[[1.0, 0.0], [0.0, 1.0]]);
+        nn.set_input(&data);
+        nn.set_lbl(&lbl_onehot);
+        let loss = nn.forward(false);
+        let guess = nn.guess(&data);
+
+        // With teacher
+        let mut imported = NeuralNetwork::import(&nn.export_to_string().unwrap());
+        imported.set_input(&data);
+        imported.set_lbl(&lbl_onehot);
+        assert_eq!(round_digit_arr2(&imported.forward(false), -6), round_digit_arr2(&loss, -6));
+
+        // Student only
+        let mut student = NeuralNetwork::import(&nn.export_inference_to_string().unwrap());
+        assert_eq!(round_digit_arr2(&student.guess(&data), -6), round_digit_arr2(&guess, -6));
+        student.set_lbl(&lbl_onehot);
+        assert_eq!(student.forward(false).shape(), [2, 1]);
+    }
+}
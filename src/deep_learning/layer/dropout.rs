@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -6,7 +5,9 @@ use ndarray::prelude::{
 use rand::Rng;
 
 use crate::deep_learning::*;
+use crate::deep_learning::common::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 
 // Dropout
 pub struct Dropout {
@@ -31,29 +32,30 @@ impl Dropout {
     pub fn layer_label() -> &'static str {
         "dropout"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
         // dropout_rate
-        let value_line = lines.next().unwrap();
-        let dropout_rate = value_line.parse::<f64>().unwrap();
+        let value_line = next_import_value(lines)?;
+        let dropout_rate = value_line.parse::<f64>()?;
 
         // is_learning
-        let value_line = lines.next().unwrap();
-        let is_learning = value_line.parse::<i32>().unwrap() == 1i32;
+        let value_line = next_import_value(lines)?;
+        let is_learning = value_line.parse::<i32>()? == 1i32;
 
-        let x = neural_network::import_network_layer(lines);
-        let filter = neural_network::import_network_layer(lines);
-        let bias = neural_network::import_network_layer(lines);
+        let x = neural_network::import_network_layer(lines)?;
+        let filter = neural_network::import_network_layer(lines)?;
+        let bias = neural_network::import_network_layer(lines)?;
 
-        Dropout {
-            x: x,
-            y: None,
-            mask: None,
-            dropout_rate: dropout_rate,
-            is_learning: is_learning,
-        }
+        Ok(
+            Dropout {
+                x: x,
+                y: None,
+                mask: None,
+                dropout_rate: dropout_rate,
+                is_learning: is_learning,
+            }
+        )
     }
 }
 impl NetworkLayer for Dropout {
@@ -111,8 +113,19 @@ impl NetworkLayer for Dropout {
     fn weight_sum(&self) -> f64 {
         return self.x.weight_sum();
     }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let output_shape = last_output_shape(summaries);
+        summaries.push(LayerSummary::new("Dropout", Self::layer_label(), &output_shape, (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
 
         writeln!(file, "{}", self.dropout_rate)?;
@@ -1,10 +1,13 @@
 
 #[cfg (not (target_family = "wasm"))]
-use std::fs::File;
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
 
+use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
+
 #[cfg_attr(test, mockall::automock)]
 pub trait NetworkLayer {
     fn forward(&mut self, is_learning: bool) -> Array2<f64>;
@@ -17,9 +20,22 @@ pub trait NetworkLayer {
     fn plot(&self);
     fn weight_squared_sum(&self) -> f64;
     fn weight_sum(&self) -> f64;
+    // Enable or disable updating the parameters owned by this layer
+    fn set_trainable(&mut self, _trainable: bool) {}
+    // Keep parameters of the `trainable_num` layers closest to the output trainable and freeze the rest
+    fn freeze_except_top(&mut self, trainable_num: usize);
+    // Call visitor with every parameter below this layer and its name (e.g. "1.affine/w").
+    // Returns the index of this layer counted from the input side like summarize.
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize;
+    // Append summaries of the layers below and this layer (input side first)
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>);
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, _file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        Err(format!("{} can not be exported", std::any::type_name::<Self>()).into())
+    }
+    // Layers needed only for inference. Loss layers used only in learning override this
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
-        File::open("aaaaaaa")?;
-        Ok(())
+    fn export_inference(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        self.export(file)
     }
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::layer::*;
+use crate::deep_learning::optimizer::*;
+
+// Layer holding a trainable tensor (weight, bias, filter, ...)
+pub trait Parameter: NetworkLayer {
+    fn get_name(&self) -> &str;
+    fn get_value(&self) -> &Array2<f64>;
+    fn get_value_mut(&mut self) -> &mut Array2<f64>;
+    // Gradient accumulated by backward since the last zero_grad
+    fn get_gradient(&self) -> Option<&Array2<f64>>;
+    fn get_gradient_mut(&mut self) -> Option<&mut Array2<f64>>;
+    fn zero_grad(&mut self);
+    // Update value with the accumulated gradient by the optimizer
+    fn step(&mut self);
+    fn is_trainable(&self) -> bool;
+    // Learning rate of the optimizer
+    fn get_learning_rate(&self) -> f64;
+    fn set_learning_rate(&mut self, learning_rate: f64);
+    // Optimizer with its state. Replaced to restore a snapshot of the state.
+    fn get_optimizer(&self) -> &dyn Optimizer;
+    fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>);
+}
+
+pub trait ParameterVisitor {
+    fn visit(&mut self, name: &str, parameter: &mut dyn Parameter);
+}
+impl<F> ParameterVisitor for F
+    where F: FnMut(&str, &mut dyn Parameter)
+{
+    fn visit(&mut self, name: &str, parameter: &mut dyn Parameter) {
+        self(name, parameter);
+    }
+}
+
+// Join hierarchical parameter name. e.g. "1.affine" + "w" -> "1.affine/w"
+pub fn join_parameter_name(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+// Name of a layer in parameter names. Counted from the input side, so stacking layers on top keeps the names.
+// e.g. 1 + "affine" -> "1.affine"
+pub fn layer_parameter_prefix(layer_index: usize, layer_label: &str) -> String {
+    format!("{}.{}", layer_index, layer_label)
+}
+
+// Label of the layer holding the parameter. e.g. "1.affine/w" -> "affine"
+pub fn parameter_layer_label(name: &str) -> &str {
+    let layer = name.split('/').next().unwrap_or("");
+    match layer.find('.') {
+        Some(i) => &layer[i + 1..],
+        None => layer,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_join_parameter_name() {
+        assert_eq!(join_parameter_name("", "w"), "w");
+        assert_eq!(join_parameter_name("1.affine", "w"), "1.affine/w");
+    }
+
+    #[test]
+    fn test_layer_parameter_prefix() {
+        assert_eq!(layer_parameter_prefix(1, "affine"), "1.affine");
+        assert_eq!(parameter_layer_label("1.affine/w"), "affine");
+        assert_eq!(parameter_layer_label("12.batch_norm/b"), "batch_norm");
+    }
+}
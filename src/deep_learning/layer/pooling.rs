@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::{
     Array1,
@@ -8,7 +7,9 @@ use ndarray::{
 };
 
 use crate::deep_learning::*;
+use crate::deep_learning::common::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 // use crate::deep_learning::optimizer::*;
 // use crate::deep_learning::common::*;
 
@@ -39,62 +40,68 @@ impl Pooling {
             col_max_index: None,
         }
     }
+    // x_shape for the batch size of the current input
+    fn batch_x_shape(&self, batch_num: usize) -> (usize, usize, usize, usize) {
+        (batch_num, self.x_shape.1, self.x_shape.2, self.x_shape.3)
+    }
     pub fn layer_label() -> &'static str {
         "pooling"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
         // x_shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
         let x_shape: (usize, usize, usize, usize) = (
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
-            shape_line_split.next().unwrap().parse::<usize>().unwrap(),
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
+            next_import_value(&mut shape_line_split)?.parse::<usize>()?,
         );
 
         // filter_h
-        let value_line = lines.next().unwrap();
-        let filter_h = value_line.parse::<usize>().unwrap();
+        let value_line = next_import_value(lines)?;
+        let filter_h = value_line.parse::<usize>()?;
 
         // filter_w
-        let value_line = lines.next().unwrap();
-        let filter_w = value_line.parse::<usize>().unwrap();
+        let value_line = next_import_value(lines)?;
+        let filter_w = value_line.parse::<usize>()?;
 
         // stride
-        let value_line = lines.next().unwrap();
-        let stride = value_line.parse::<usize>().unwrap();
+        let value_line = next_import_value(lines)?;
+        let stride = value_line.parse::<usize>()?;
 
         // padding
-        let value_line = lines.next().unwrap();
-        let padding = value_line.parse::<usize>().unwrap();
-
-        let x = neural_network::import_network_layer(lines);
-
-        Pooling {
-            x: x,
-            y: None,
-            x_shape: x_shape,
-            filter_h: filter_h,
-            filter_w: filter_w,
-            stride: stride,
-            padding: padding,
-            col_max_index: None,
-        }
+        let value_line = next_import_value(lines)?;
+        let padding = value_line.parse::<usize>()?;
+
+        let x = neural_network::import_network_layer(lines)?;
+
+        Ok(
+            Pooling {
+                x: x,
+                y: None,
+                x_shape: x_shape,
+                filter_h: filter_h,
+                filter_w: filter_w,
+                stride: stride,
+                padding: padding,
+                col_max_index: None,
+            }
+        )
     }
 }
 impl NetworkLayer for Pooling {
     fn forward(&mut self, is_learning: bool) -> Array2<f64> {
         if self.y.is_none() {
             let x = self.x.forward(is_learning);
-            let (batch_num, channel_num, x_h, x_w) = self.x_shape;
+            let x_shape = self.batch_x_shape(x.shape()[0]);
+            let (batch_num, channel_num, x_h, x_w) = x_shape;
             let step_h = (x_h + 2 * self.padding - self.filter_h) / self.stride + 1;
             let step_w = (x_w + 2 * self.padding - self.filter_w) / self.stride + 1;
 
-            let x_4d = x.to_shared().reshape(self.x_shape).to_owned();
+            let x_4d = x.to_shared().reshape(x_shape).to_owned();
             let col = im2col(&x_4d, self.filter_h, self.filter_w, self.stride, self.padding);
             let shaped_col = col.to_shared().reshape((batch_num*channel_num*step_h*step_w , self.filter_h*self.filter_w));
 
@@ -125,7 +132,8 @@ impl NetworkLayer for Pooling {
         self.forward(true);
         let col_max_index = self.col_max_index.as_ref().unwrap();
 
-        let (batch_num, channel_num, x_h, x_w) = self.x_shape;
+        let x_shape = self.batch_x_shape(dout.shape()[0]);
+        let (batch_num, channel_num, x_h, x_w) = x_shape;
         let step_h = (x_h + 2 * self.padding - self.filter_h) / self.stride + 1;
         let step_w = (x_w + 2 * self.padding - self.filter_w) / self.stride + 1;
 
@@ -140,7 +148,7 @@ impl NetworkLayer for Pooling {
         }
         let col_dx = col_dx;
 
-        let dx_4d = col2im(&col_dx, self.x_shape, (0, 0, self.filter_h, self.filter_w), self.stride, self.padding);
+        let dx_4d = col2im(&col_dx, x_shape, (0, 0, self.filter_h, self.filter_w), self.stride, self.padding);
 
         let dx = dx_4d.to_shared().reshape((batch_num, channel_num*x_h*x_w)).to_owned();
 
@@ -169,8 +177,22 @@ impl NetworkLayer for Pooling {
     fn weight_sum(&self) -> f64 {
         return self.x.weight_sum();
     }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let batch_num = last_output_shape(summaries)[0];
+        let (_, channel_num, x_h, x_w) = self.x_shape;
+        let step_h = (x_h + 2 * self.padding - self.filter_h) / self.stride + 1;
+        let step_w = (x_w + 2 * self.padding - self.filter_w) / self.stride + 1;
+        summaries.push(LayerSummary::new("Pooling", Self::layer_label(), &[batch_num, channel_num * step_h * step_w], (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
 
         writeln!(file, "{},{},{},{}", self.x_shape.0, self.x_shape.1, self.x_shape.2, self.x_shape.3)?;
@@ -194,6 +216,7 @@ mod test {
         Array,
         arr2,
     };
+    use crate::deep_learning::gradient_check::*;
 
     #[test]
     fn test_pooling_forward() {
@@ -402,4 +425,39 @@ mod test {
         );
         println!("{:?}", aa);
     }
+
+    #[test]
+    fn test_gradient_check() {
+        // B:2, C:2 H:5 W:5
+        // Distinct values to keep max position while numerical differentiation
+        let input = Array2::from_shape_vec(
+            (2, 2*5*5),
+            (0..2*2*5*5).map(|i| ((i * 37) % 100) as f64 / 10.0 - 5.0).collect()
+        ).unwrap();
+        // B:2, C:2 H:3 W:3
+        let dout = Array2::from_shape_vec((2, 2*3*3), norm_random_vec(2*2*3*3)).unwrap();
+
+        let results = check_gradient(|x| Pooling::new(x, (2, 2, 5, 5), 3, 3, 2, 1), input, &dout);
+
+        assert_eq!(results.len(), 1);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
+
+    #[test]
+    fn test_pooling_partial_batch() {
+        // Built for B:3 but given B:1
+        let value = DirectValue::new(Array2::<f64>::zeros((3, 2*2*2)));
+        let mut pooling = Pooling::new(value, (3, 2, 2, 2), 2, 2, 2, 0);
+
+        pooling.set_value(&arr2(&
+            [
+                [1.0, 4.0, 3.0, 2.0,    -1.0, -2.0, -3.0, -4.0],
+            ]
+        ));
+        assert_eq!(pooling.forward(false), arr2(&[[4.0, -1.0]]));
+
+        pooling.backward(arr2(&[[1.0, 2.0]]));
+    }
 }
\ No newline at end of file
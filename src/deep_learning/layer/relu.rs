@@ -1,5 +1,4 @@
 
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -7,6 +6,7 @@ use ndarray::prelude::{
 
 use crate::deep_learning::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 
 // Relu
 // y = x (x > 0)
@@ -28,16 +28,17 @@ impl Relu {
     pub fn layer_label() -> &'static str {
         "relu"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
-        let x = neural_network::import_network_layer(lines);
+        let x = neural_network::import_network_layer(lines)?;
 
-        Relu {
-            x: x,
-            y: None,
-        }
+        Ok(
+            Relu {
+                x: x,
+                y: None,
+            }
+        )
     }
 }
 impl NetworkLayer for Relu {
@@ -87,8 +88,19 @@ impl NetworkLayer for Relu {
     fn weight_sum(&self) -> f64 {
         return self.x.weight_sum();
     }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let output_shape = last_output_shape(summaries);
+        summaries.push(LayerSummary::new("Relu", Self::layer_label(), &output_shape, (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
         file.flush()?;
         self.x.export(file)?;
@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -7,6 +6,7 @@ use std::f64::consts::E;
 
 use crate::deep_learning::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 
 // Sigmoid
 // y = 1 / (1 + exp(-x))
@@ -26,16 +26,17 @@ impl Sigmoid {
     pub fn layer_label() -> &'static str {
         "sigmoid"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
-        let x = neural_network::import_network_layer(lines);
+        let x = neural_network::import_network_layer(lines)?;
 
-        Sigmoid {
-            x: x,
-            y: None,
-        }
+        Ok(
+            Sigmoid {
+                x: x,
+                y: None,
+            }
+        )
     }
 }
 impl NetworkLayer for Sigmoid {
@@ -91,8 +92,19 @@ impl NetworkLayer for Sigmoid {
     fn weight_sum(&self) -> f64 {
         return self.x.weight_sum();
     }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        let output_shape = last_output_shape(summaries);
+        summaries.push(LayerSummary::new("Sigmoid", Self::layer_label(), &output_shape, (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
         file.flush()?;
         self.x.export(file)?;
@@ -110,6 +122,7 @@ mod test_sigmoid_mod {
 
     use crate::deep_learning::common::*;
     use crate::deep_learning::optimizer::*;
+    use crate::deep_learning::gradient_check::*;
 
     #[test]
     fn test_forward() {
@@ -153,11 +166,27 @@ mod test_sigmoid_mod {
         );
 
         sigmoid.backward(dout.clone());
+        sigmoid.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+            parameter.step();
+        });
 
         assert_eq!(
             round_digit_arr2(&sigmoid.x.forward(true), -4),
             // (1 - f(x)) f(x)
             round_digit_arr2(&(arr2_value.clone()-(((1.0-sigmoid.forward(true))*sigmoid.forward(true))*dout*0.01)), -4)
         );
-    } 
-}
\ No newline at end of file
+    }
+
+    #[test]
+    fn test_gradient_check() {
+        let input = Array2::from_shape_vec((3, 4), norm_random_vec(3 * 4)).unwrap();
+        let dout = Array2::from_shape_vec((3, 4), norm_random_vec(3 * 4)).unwrap();
+
+        let results = check_gradient(|x| Sigmoid::new(x), input, &dout);
+
+        assert_eq!(results.len(), 1);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
+}
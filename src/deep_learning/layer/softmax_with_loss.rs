@@ -1,5 +1,4 @@
 use std::f64::consts::E;
-use std::fs::File;
 use std::io::{self, Read, Write, BufReader, BufRead, Lines};
 use ndarray::prelude::{
     Array2,
@@ -8,6 +7,7 @@ use ndarray::Axis;
 
 use crate::deep_learning::*;
 use crate::deep_learning::layer::*;
+use crate::deep_learning::summary::*;
 use crate::deep_learning::common::*;
 
 
@@ -32,31 +32,32 @@ impl SoftmaxWithLoss {
     pub fn layer_label() -> &'static str {
         "softmax"
     }
-    pub fn import<'a, T>(lines: &mut T) -> Self
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
         where T: Iterator<Item = &'a str>
     {
-        println!("import {}", Self::layer_label());
         // t shape
-        let shape_line = lines.next().unwrap();
+        let shape_line = next_import_value(lines)?;
         let mut shape_line_split = shape_line.split(',');
-        let dim: (usize, usize) = (shape_line_split.next().unwrap().parse::<usize>().unwrap(), shape_line_split.next().unwrap().parse::<usize>().unwrap());
+        let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
         // t
         let mut t = Array2::<f64>::zeros(dim);
         for row_i in 0..dim.0 {
-            let line = lines.next().unwrap();
+            let line = next_import_value(lines)?;
             let mut line_split = line.split(',');
             for col_i in 0..dim.1 {
-                t[(row_i, col_i)] = line_split.next().unwrap().parse::<f64>().unwrap();
+                t[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
             }
         }
 
-        let x = neural_network::import_network_layer(lines);
+        let x = neural_network::import_network_layer(lines)?;
 
-        SoftmaxWithLoss {
-            x: x,
-            t: t,
-            z: None,
-        }
+        Ok(
+            SoftmaxWithLoss {
+                x: x,
+                t: t,
+                z: None,
+            }
+        )
     }
 }
 impl NetworkLayer for SoftmaxWithLoss {
@@ -99,10 +100,11 @@ impl NetworkLayer for SoftmaxWithLoss {
         self.clean();
     }
     fn set_lbl(&mut self, value: &Array2<f64>) {
-        if self.t.shape() != value.shape() {
+        // Batch size (row count) can change
+        if self.t.shape()[1] != value.shape()[1] {
             panic!("Different shape. self.t: {:?} value:{:?}", self.t.shape(), value.shape());
         }
-        self.t.assign(value);
+        self.t = value.clone();
         self.x.set_lbl(value);
         self.clean();
     }
@@ -118,8 +120,20 @@ impl NetworkLayer for SoftmaxWithLoss {
     fn weight_sum(&self) -> f64 {
         return self.x.weight_sum();
     }
+    fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.x.freeze_except_top(trainable_num);
+    }
+    fn visit_parameters(&mut self, visitor: &mut dyn ParameterVisitor) -> usize {
+        self.x.visit_parameters(visitor) + 1
+    }
+    fn summarize(&mut self, summaries: &mut Vec<LayerSummary>) {
+        self.x.summarize(summaries);
+        // Loss of each row
+        let output_shape = [last_output_shape(summaries)[0], 1];
+        summaries.push(LayerSummary::new("SoftmaxWithLoss", Self::layer_label(), &output_shape, (0, 0)));
+    }
     #[cfg (not (target_family = "wasm"))]
-    fn export(&self, file: &mut File) -> Result<(), Box<std::error::Error>> {
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
         writeln!(file, "{}", Self::layer_label())?;
 
         writeln!(file, "{},{}", self.t.shape()[0], self.t.shape()[1])?;
@@ -136,7 +150,7 @@ impl NetworkLayer for SoftmaxWithLoss {
     }
 }
 
-fn softmax(x: &Array2<f64>) -> Array2<f64> {
+pub fn softmax(x: &Array2<f64>) -> Array2<f64> {
     // Create Array same shape from x
     let mut z = Array2::<f64>::zeros(x.dim());
     // let mut z = x.clone();
@@ -189,7 +203,7 @@ fn softmax(x: &Array2<f64>) -> Array2<f64> {
     return z;
 }
 
-fn crosss_entropy_error(x: &Array2<f64>, t: &Array2<f64>) -> Array2<f64> {
+pub fn crosss_entropy_error(x: &Array2<f64>, t: &Array2<f64>) -> Array2<f64> {
     if x.len() != t.len() {
         panic!("Different shape. x:{:?} t:{:?}", x.shape(), t.shape());
     }
@@ -226,6 +240,7 @@ mod test_softmax_with_loss_mod {
     };
 
     use crate::deep_learning::optimizer::*;
+    use crate::deep_learning::gradient_check::*;
 
     #[test]
     fn test_softmax() {
@@ -300,10 +315,33 @@ mod test_softmax_with_loss_mod {
         );
 
         softmaxLoss.backward(dout.clone());
+        softmaxLoss.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+            parameter.step();
+        });
 
         assert_eq!(
             round_digit_arr2(&softmaxLoss.x.forward(true), -4),
             round_digit_arr2(&(arr2_x.clone()-((softmax(&arr2_x)-t)*dout*0.01)), -4)
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_gradient_check() {
+        let input = Array2::from_shape_vec((3, 4), norm_random_vec(3 * 4)).unwrap();
+        let t = arr2(&
+            [
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 0.0],
+            ]
+        );
+        let dout = Array2::<f64>::ones((3, 1));
+
+        let results = check_gradient(|x| SoftmaxWithLoss::new(x, t), input, &dout);
+
+        assert_eq!(results.len(), 1);
+        for result in &results {
+            assert!(result.max_relative_error < 10f64.powi(-5), "{}: {}", result.name, result.max_relative_error);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use ndarray::prelude::{
+    Array2,
+};
+use rand::{
+    SeedableRng,
+    rngs::StdRng,
+};
+
+use crate::deep_learning::graph_plotter::*;
+use crate::deep_learning::neural_network::*;
+
+// Weight of the past losses in the moving average
+const SMOOTHING: f64 = 0.98;
+// Stop when the smoothed loss exceeds the best one by this factor
+const DIVERGENCE_FACTOR: f64 = 4.0;
+
+pub struct LearningRateFinderParameter {
+    pub batch_size: usize,
+    pub min_learning_rate: f64,
+    pub max_learning_rate: f64,
+    pub steps_num: u32,
+    // Seed of minibatch sampling. Random if None
+    pub seed: Option<u64>,
+    // Plot smoothed loss against learning rate
+    pub plot: bool,
+}
+
+pub struct LearningRateFinderResult {
+    pub learning_rates: Vec<f64>,
+    // Loss of the learned minibatch at each step
+    pub losses: Vec<f64>,
+    // Exponential moving average of losses
+    pub smoothed_losses: Vec<f64>,
+    // Learning rate where the smoothed loss decreases most steeply
+    pub suggested_learning_rate: Option<f64>,
+}
+
+// Learning rate of step in [0, steps_num) increasing exponentially from min to max
+pub fn exponential_learning_rate(min_learning_rate: f64, max_learning_rate: f64, steps_num: u32, step: u32) -> f64 {
+    if steps_num < 2 {
+        return min_learning_rate;
+    }
+    let ratio = step as f64 / (steps_num - 1) as f64;
+    return min_learning_rate * (max_learning_rate / min_learning_rate).powf(ratio);
+}
+
+// Train with all learning rates set to the same increasing value and record the loss.
+// Parameter values, learning rates and state of optimizers (e.g. momentum) are restored at the end.
+#[cfg (not (target_family = "wasm"))]
+pub fn find_learning_rate(
+    network: &mut NeuralNetwork,
+    parameter: &LearningRateFinderParameter,
+    trn_data: &Array2<f64>,
+    trn_lbl_onehot: &Array2<f64>,
+) -> Result<LearningRateFinderResult, Box<dyn std::error::Error>> {
+    if parameter.min_learning_rate <= 0.0 || parameter.min_learning_rate >= parameter.max_learning_rate {
+        panic!("Invalid learning rate range. min: {} max: {}", parameter.min_learning_rate, parameter.max_learning_rate);
+    }
+
+    let initial_values = network.parameter_values();
+    let initial_learning_rates = network.learning_rates();
+    let initial_optimizer_states = network.optimizer_states()?;
+    let ones = vec![1f64; initial_learning_rates.len()];
+
+    let mut rng = match parameter.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut learning_rates = Vec::<f64>::new();
+    let mut losses = Vec::<f64>::new();
+    let mut smoothed_losses = Vec::<f64>::new();
+    let mut average = 0f64;
+    let mut best_loss = f64::INFINITY;
+    for step in 0..parameter.steps_num {
+        let learning_rate = exponential_learning_rate(parameter.min_learning_rate, parameter.max_learning_rate, parameter.steps_num, step);
+        network.scale_learning_rates(&ones, learning_rate);
+
+        let (batch_data, batch_lbl_onehot) = make_minibatch_data_with_rng(parameter.batch_size, trn_data, trn_lbl_onehot, &mut rng);
+        network.zero_grad();
+        let loss = network.compute_gradient(&batch_data, &batch_lbl_onehot);
+        network.step();
+
+        // Bias corrected moving average
+        average = SMOOTHING * average + (1.0 - SMOOTHING) * loss;
+        let smoothed_loss = average / (1.0 - SMOOTHING.powi(step as i32 + 1));
+
+        learning_rates.push(learning_rate);
+        losses.push(loss);
+        smoothed_losses.push(smoothed_loss);
+
+        if !smoothed_loss.is_finite() || smoothed_loss > best_loss * DIVERGENCE_FACTOR {
+            break;
+        }
+        best_loss = best_loss.min(smoothed_loss);
+    }
+
+    network.set_parameter_values(&initial_values);
+    network.scale_learning_rates(&initial_learning_rates, 1.0);
+    network.set_optimizer_states(&initial_optimizer_states)?;
+
+    if parameter.plot {
+        let res = plot_learning_rate_loss(learning_rates.clone(), smoothed_losses.clone(), "learning_rate");
+        if let Err(e) = res {
+            network.print_progress(format!("{}", e));
+        }
+    }
+
+    let suggested_learning_rate = suggest_learning_rate(&learning_rates, &smoothed_losses);
+    Ok(
+        LearningRateFinderResult {
+            learning_rates: learning_rates,
+            losses: losses,
+            smoothed_losses: smoothed_losses,
+            suggested_learning_rate: suggested_learning_rate,
+        }
+    )
+}
+
+// Learning rate of the steepest negative slope of loss against log(learning rate)
+pub fn suggest_learning_rate(learning_rates: &[f64], losses: &[f64]) -> Option<f64> {
+    let mut suggested = None;
+    let mut min_slope = 0f64;
+    for i in 1..learning_rates.len().min(losses.len()) {
+        if !losses[i].is_finite() || !losses[i - 1].is_finite() {
+            continue;
+        }
+        let slope = (losses[i] - losses[i - 1]) / (learning_rates[i].ln() - learning_rates[i - 1].ln());
+        if slope < min_slope {
+            min_slope = slope;
+            suggested = Some(learning_rates[i]);
+        }
+    }
+    return suggested;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+    use crate::deep_learning::layer::*;
+    use crate::deep_learning::optimizer::*;
+
+    #[test]
+    fn test_exponential_learning_rate() {
+        assert_eq!(exponential_learning_rate(0.001, 1.0, 4, 0), 0.001);
+        assert_eq!(round_digit(exponential_learning_rate(0.001, 1.0, 4, 1), -6), round_digit(0.01, -6));
+        assert_eq!(round_digit(exponential_learning_rate(0.001, 1.0, 4, 2), -6), round_digit(0.1, -6));
+        assert_eq!(round_digit(exponential_learning_rate(0.001, 1.0, 4, 3), -6), round_digit(1.0, -6));
+    }
+
+    #[test]
+    fn test_suggest_learning_rate() {
+        let learning_rates = vec![0.001, 0.01, 0.1, 1.0, 10.0];
+        let losses = vec![2.0, 1.9, 1.0, 0.8, 5.0];
+
+        assert_eq!(suggest_learning_rate(&learning_rates, &losses), Some(0.1));
+        // Loss never decreases
+        assert_eq!(suggest_learning_rate(&learning_rates, &[1.0, 2.0, 3.0, 4.0, 5.0]), None);
+    }
+
+    #[test]
+    fn test_find_learning_rate() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            2,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let values = nn.parameter_values();
+        let result = find_learning_rate(
+            &mut nn,
+            &LearningRateFinderParameter {
+                batch_size: batch_size,
+                min_learning_rate: 0.0001,
+                max_learning_rate: 1.0,
+                steps_num: 20,
+                seed: Some(0),
+                plot: false,
+            },
+            &data,
+            &lbl_onehot,
+        ).unwrap();
+
+        assert!(result.learning_rates.len() > 0 && result.learning_rates.len() <= 20);
+        assert_eq!(result.losses.len(), result.learning_rates.len());
+        assert_eq!(result.smoothed_losses.len(), result.learning_rates.len());
+        assert_eq!(result.learning_rates[0], 0.0001);
+        for i in 1..result.learning_rates.len() {
+            assert!(result.learning_rates[i - 1] < result.learning_rates[i]);
+        }
+        // First smoothed loss equals the loss by bias correction
+        assert_eq!(round_digit(result.smoothed_losses[0], -6), round_digit(result.losses[0], -6));
+        if let Some(learning_rate) = result.suggested_learning_rate {
+            assert!(result.learning_rates.contains(&learning_rate));
+        }
+
+        // Restored
+        assert_eq!(nn.parameter_values(), values);
+        assert_eq!(nn.learning_rates(), vec![0.1, 0.1]);
+    }
+
+    #[test]
+    fn test_find_learning_rate_restore_optimizer() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            2,
+            Momentum::new(0.1, 0.9),
+            Momentum::new(0.1, 0.9)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+        nn.set_quiet(true);
+
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        // Momentum has velocity before the search
+        nn.zero_grad();
+        nn.compute_gradient(&data, &lbl_onehot);
+        nn.step();
+        let mut expected = NeuralNetwork::import(&nn.export_to_string().unwrap());
+
+        find_learning_rate(
+            &mut nn,
+            &LearningRateFinderParameter {
+                batch_size: batch_size,
+                min_learning_rate: 0.01,
+                max_learning_rate: 100.0,
+                steps_num: 10,
+                seed: Some(0),
+                plot: false,
+            },
+            &data,
+            &lbl_onehot,
+        ).unwrap();
+
+        // Next update continues from the velocity before the search
+        for network in [&mut nn, &mut expected].iter_mut() {
+            network.zero_grad();
+            network.compute_gradient(&data, &lbl_onehot);
+            network.step();
+        }
+        assert_eq!(nn.parameter_values(), expected.parameter_values());
+    }
+}
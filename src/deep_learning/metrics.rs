@@ -0,0 +1,261 @@
+use std::fmt;
+use ndarray::prelude::{
+    Array2,
+    Axis,
+};
+
+use crate::deep_learning::common::*;
+
+// Classification metrics accumulated from forward_skip_loss outputs and one-hot labels
+pub struct ClassificationMetrics {
+    // confusion_matrix[(actual, predicted)]
+    pub confusion_matrix: Array2<usize>,
+    // rank_counts[r]: number of samples whose correct label has the (r+1)-th largest output
+    rank_counts: Vec<usize>,
+}
+
+impl ClassificationMetrics {
+    pub fn new(class_num: usize) -> Self {
+        ClassificationMetrics {
+            confusion_matrix: Array2::<usize>::zeros((class_num, class_num)),
+            rank_counts: vec![0; class_num],
+        }
+    }
+
+    pub fn add_batch(&mut self, result: &Array2<f64>, lbl_onehot: &Array2<f64>) {
+        if result.shape() != lbl_onehot.shape() {
+            panic!("Different shape. result: {:?} lbl_onehot:{:?}", result.shape(), lbl_onehot.shape());
+        }
+
+        for row_i in 0..result.shape()[0] {
+            let result_row = result.index_axis(Axis(0), row_i).to_owned();
+            let actual = max_index_in_arr1(&lbl_onehot.index_axis(Axis(0), row_i).to_owned());
+            let predicted = max_index_in_arr1(&result_row);
+            self.confusion_matrix[(actual, predicted)] += 1;
+
+            // Count outputs larger than the correct one
+            let rank = result_row.iter().filter(|v| **v > result_row[actual]).count();
+            self.rank_counts[rank] += 1;
+        }
+    }
+
+    pub fn class_num(&self) -> usize {
+        self.confusion_matrix.shape()[0]
+    }
+
+    pub fn sample_num(&self) -> usize {
+        self.confusion_matrix.sum()
+    }
+
+    fn true_positive(&self, class: usize) -> usize {
+        self.confusion_matrix[(class, class)]
+    }
+
+    fn predicted_num(&self, class: usize) -> usize {
+        self.confusion_matrix.index_axis(Axis(1), class).sum()
+    }
+
+    // Samples of the class (support)
+    pub fn actual_num(&self, class: usize) -> usize {
+        self.confusion_matrix.index_axis(Axis(0), class).sum()
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let correct_num: usize = (0..self.class_num()).map(|c| self.true_positive(c)).sum();
+        return safe_div(correct_num as f64, self.sample_num() as f64);
+    }
+
+    pub fn precision(&self, class: usize) -> f64 {
+        safe_div(self.true_positive(class) as f64, self.predicted_num(class) as f64)
+    }
+
+    pub fn recall(&self, class: usize) -> f64 {
+        safe_div(self.true_positive(class) as f64, self.actual_num(class) as f64)
+    }
+
+    pub fn f1(&self, class: usize) -> f64 {
+        f1(self.precision(class), self.recall(class))
+    }
+
+    // Unweighted average of the classes
+    pub fn macro_precision(&self) -> f64 {
+        (0..self.class_num()).map(|c| self.precision(c)).sum::<f64>() / self.class_num() as f64
+    }
+
+    pub fn macro_recall(&self) -> f64 {
+        (0..self.class_num()).map(|c| self.recall(c)).sum::<f64>() / self.class_num() as f64
+    }
+
+    pub fn macro_f1(&self) -> f64 {
+        (0..self.class_num()).map(|c| self.f1(c)).sum::<f64>() / self.class_num() as f64
+    }
+
+    // Calculated from the total counts.
+    // Each sample has one label and one prediction, so every micro average equals accuracy.
+    pub fn micro_precision(&self) -> f64 {
+        let true_positive: usize = (0..self.class_num()).map(|c| self.true_positive(c)).sum();
+        let predicted_num: usize = (0..self.class_num()).map(|c| self.predicted_num(c)).sum();
+        return safe_div(true_positive as f64, predicted_num as f64);
+    }
+
+    pub fn micro_recall(&self) -> f64 {
+        let true_positive: usize = (0..self.class_num()).map(|c| self.true_positive(c)).sum();
+        let actual_num: usize = (0..self.class_num()).map(|c| self.actual_num(c)).sum();
+        return safe_div(true_positive as f64, actual_num as f64);
+    }
+
+    pub fn micro_f1(&self) -> f64 {
+        f1(self.micro_precision(), self.micro_recall())
+    }
+
+    // Rate of samples whose correct label is in the k largest outputs
+    pub fn top_k_accuracy(&self, k: usize) -> f64 {
+        let correct_num: usize = self.rank_counts.iter().take(k).sum();
+        return safe_div(correct_num as f64, self.sample_num() as f64);
+    }
+}
+
+impl fmt::Display for ClassificationMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<8}{:>12}{:>12}{:>12}{:>12}", "Class", "Precision", "Recall", "F1", "Support")?;
+        writeln!(f, "{}", "=".repeat(56))?;
+        for class in 0..self.class_num() {
+            writeln!(
+                f,
+                "{:<8}{:>12.4}{:>12.4}{:>12.4}{:>12}",
+                class,
+                self.precision(class),
+                self.recall(class),
+                self.f1(class),
+                self.actual_num(class)
+            )?;
+        }
+        writeln!(f, "{}", "=".repeat(56))?;
+        writeln!(f, "{:<8}{:>12.4}{:>12.4}{:>12.4}{:>12}", "Macro", self.macro_precision(), self.macro_recall(), self.macro_f1(), self.sample_num())?;
+        writeln!(f, "{:<8}{:>12.4}{:>12.4}{:>12.4}{:>12}", "Micro", self.micro_precision(), self.micro_recall(), self.micro_f1(), self.sample_num())?;
+        writeln!(f, "")?;
+
+        // Confusion matrix (row: actual, column: predicted)
+        write!(f, "{:<8}", "")?;
+        for class in 0..self.class_num() {
+            write!(f, "{:>6}", class)?;
+        }
+        writeln!(f, "")?;
+        for actual in 0..self.class_num() {
+            write!(f, "{:<8}", actual)?;
+            for predicted in 0..self.class_num() {
+                write!(f, "{:>6}", self.confusion_matrix[(actual, predicted)])?;
+            }
+            writeln!(f, "")?;
+        }
+        write!(f, "Top-1: {:.4} Top-3: {:.4}", self.top_k_accuracy(1), self.top_k_accuracy(3))
+    }
+}
+
+fn safe_div(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0f64 {
+        return 0f64;
+    }
+    return numerator / denominator;
+}
+
+fn f1(precision: f64, recall: f64) -> f64 {
+    safe_div(2.0 * precision * recall, precision + recall)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    fn metrics() -> ClassificationMetrics {
+        let mut metrics = ClassificationMetrics::new(3);
+        metrics.add_batch(
+            &arr2(&
+                [
+                    [0.7, 0.2, 0.1],    // 0 -> 0
+                    [0.5, 0.4, 0.1],    // 1 -> 0
+                    [0.1, 0.8, 0.1],    // 1 -> 1
+                    [0.2, 0.3, 0.5],    // 2 -> 2
+                    [0.1, 0.6, 0.3],    // 2 -> 1
+                    [0.6, 0.35, 0.05],  // 2 -> 0
+                ]
+            ),
+            &arr2(&
+                [
+                    [1.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                    [0.0, 0.0, 1.0],
+                    [0.0, 0.0, 1.0],
+                    [0.0, 0.0, 1.0],
+                ]
+            )
+        );
+        return metrics;
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let metrics = metrics();
+
+        assert_eq!(metrics.confusion_matrix, arr2(&
+            [
+                [1, 0, 0],
+                [1, 1, 0],
+                [1, 1, 1],
+            ]
+        ));
+        assert_eq!(metrics.sample_num(), 6);
+        assert_eq!(metrics.actual_num(2), 3);
+        assert_eq!(round_digit(metrics.accuracy(), -6), round_digit(0.5, -6));
+    }
+
+    #[test]
+    fn test_precision_recall_f1() {
+        let metrics = metrics();
+
+        assert_eq!(round_digit(metrics.precision(0), -6), round_digit(1.0 / 3.0, -6));
+        assert_eq!(round_digit(metrics.recall(0), -6), round_digit(1.0, -6));
+        assert_eq!(round_digit(metrics.f1(0), -6), round_digit(0.5, -6));
+        assert_eq!(round_digit(metrics.precision(2), -6), round_digit(1.0, -6));
+        assert_eq!(round_digit(metrics.recall(2), -6), round_digit(1.0 / 3.0, -6));
+
+        assert_eq!(
+            round_digit(metrics.macro_precision(), -6),
+            round_digit((1.0 / 3.0 + 0.5 + 1.0) / 3.0, -6)
+        );
+        assert_eq!(
+            round_digit(metrics.macro_recall(), -6),
+            round_digit((1.0 + 0.5 + 1.0 / 3.0) / 3.0, -6)
+        );
+        assert_eq!(
+            round_digit(metrics.macro_f1(), -6),
+            round_digit((0.5 + 0.5 + 0.5) / 3.0, -6)
+        );
+        assert_eq!(round_digit(metrics.micro_precision(), -6), round_digit(metrics.accuracy(), -6));
+        assert_eq!(round_digit(metrics.micro_recall(), -6), round_digit(metrics.accuracy(), -6));
+        assert_eq!(round_digit(metrics.micro_f1(), -6), round_digit(metrics.accuracy(), -6));
+    }
+
+    #[test]
+    fn test_top_k_accuracy() {
+        let metrics = metrics();
+
+        assert_eq!(round_digit(metrics.top_k_accuracy(1), -6), round_digit(0.5, -6));
+        assert_eq!(round_digit(metrics.top_k_accuracy(2), -6), round_digit(5.0 / 6.0, -6));
+        assert_eq!(round_digit(metrics.top_k_accuracy(3), -6), round_digit(1.0, -6));
+    }
+
+    #[test]
+    fn test_empty() {
+        let metrics = ClassificationMetrics::new(2);
+
+        assert_eq!(metrics.accuracy(), 0.0);
+        assert_eq!(metrics.f1(0), 0.0);
+        assert_eq!(metrics.top_k_accuracy(1), 0.0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,269 @@
+use std::fs::File;
+use std::io::Write;
+use std::time::Instant;
+use serde::Serialize;
+
+use crate::deep_learning::callback::*;
+use crate::deep_learning::checkpoint::*;
+use crate::deep_learning::neural_network::*;
+
+const COLUMNS: [&str; 10] = [
+    "event",
+    "iteration",
+    "train_loss",
+    "loss",
+    "correct_rate",
+    "learning_rate_factor",
+    "gradient_norm",
+    "val_loss",
+    "val_correct_rate",
+    "elapsed_seconds",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Csv,
+    JsonLines,
+}
+
+// One line of the log. Fields are in the same order as COLUMNS.
+#[derive(Serialize)]
+struct MetricsRecord<'a> {
+    event: &'a str,
+    iteration: u32,
+    train_loss: Option<f64>,
+    loss: Option<f64>,
+    correct_rate: Option<f64>,
+    learning_rate_factor: Option<f64>,
+    gradient_norm: Option<f64>,
+    val_loss: Option<f64>,
+    val_correct_rate: Option<f64>,
+    elapsed_seconds: f64,
+}
+impl<'a> MetricsRecord<'a> {
+    // None is an empty field
+    fn to_csv(&self) -> String {
+        let optional = |v: Option<f64>| match v {
+            Some(v) => format!("{}", v),
+            None => "".to_string(),
+        };
+        let fields = vec![
+            self.event.to_string(),
+            format!("{}", self.iteration),
+            optional(self.train_loss),
+            optional(self.loss),
+            optional(self.correct_rate),
+            optional(self.learning_rate_factor),
+            optional(self.gradient_norm),
+            optional(self.val_loss),
+            optional(self.val_correct_rate),
+            format!("{}", self.elapsed_seconds),
+        ];
+        return fields.join(",");
+    }
+}
+
+// Write one record per iteration and per evaluation to a file
+pub struct MetricsLogger {
+    file: File,
+    format: LogFormat,
+    start: Instant,
+}
+impl MetricsLogger {
+    pub fn new(file_path: &str, format: LogFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = File::create(file_path)?;
+        if format == LogFormat::Csv {
+            writeln!(file, "{}", COLUMNS.join(","))?;
+            file.flush()?;
+        }
+        Ok(
+            MetricsLogger {
+                file: file,
+                format: format,
+                start: Instant::now(),
+            }
+        )
+    }
+    pub fn get_format(&self) -> LogFormat {
+        self.format
+    }
+    fn write_record(&mut self, record: &MetricsRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let line = match self.format {
+            LogFormat::Csv => record.to_csv(),
+            // NaN and infinity are written as null
+            LogFormat::JsonLines => serde_json::to_string(record)?,
+        };
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+impl TrainingCallback for MetricsLogger {
+    fn on_train_begin(&mut self, _network: &mut NeuralNetwork, _checkpoint: &Checkpoint) {
+        self.start = Instant::now();
+    }
+    fn on_iteration_end(&mut self, network: &mut NeuralNetwork, metrics: &IterationMetrics) -> bool {
+        let record = MetricsRecord {
+            event: "iteration",
+            iteration: metrics.iteration,
+            train_loss: Some(metrics.train_loss),
+            loss: Some(metrics.loss),
+            correct_rate: Some(metrics.correct_rate),
+            learning_rate_factor: metrics.learning_rate_factor,
+            gradient_norm: metrics.gradient_norm,
+            val_loss: None,
+            val_correct_rate: None,
+            elapsed_seconds: self.start.elapsed().as_secs_f64(),
+        };
+        if let Err(e) = self.write_record(&record) {
+            network.print_progress(format!("Failed to write metrics: {}", e));
+        }
+        false
+    }
+    fn on_evaluation(&mut self, network: &mut NeuralNetwork, metrics: &EvaluationMetrics) -> bool {
+        let record = MetricsRecord {
+            event: "evaluation",
+            iteration: metrics.iteration,
+            train_loss: None,
+            loss: None,
+            correct_rate: None,
+            learning_rate_factor: None,
+            gradient_norm: None,
+            val_loss: Some(metrics.val_loss),
+            val_correct_rate: Some(metrics.val_correct_rate),
+            elapsed_seconds: self.start.elapsed().as_secs_f64(),
+        };
+        if let Err(e) = self.write_record(&record) {
+            network.print_progress(format!("Failed to write metrics: {}", e));
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+    use ndarray::prelude::{
+        Array2,
+    };
+
+    use crate::deep_learning::layer::*;
+    use crate::deep_learning::optimizer::*;
+
+    fn learn_with_logger(file_path: &str, format: LogFormat) {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            2,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+        nn.set_quiet(true);
+
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        nn.learn(
+            LearningParameter {
+                batch_size: batch_size,
+                iterations_num: 4,
+                sampling: MinibatchSampling::WithReplacement,
+                gradient_clipping: None,
+                scheduler: None,
+                seed: Some(0),
+                checkpoint: None,
+                validation: Some(ValidationParameter {
+                    interval: 2,
+                    early_stopping: None,
+                    restore_best_weights: false,
+                }),
+                callbacks: vec![Box::new(MetricsLogger::new(file_path, format).unwrap())],
+                threads_num: 1,
+            },
+            LearningResource {
+                trn_data: data.clone(),
+                trn_lbl_onehot: lbl_onehot.clone(),
+                tst_data: data.clone(),
+                tst_lbl_onehot: lbl_onehot.clone(),
+                val_data: data.clone(),
+                val_lbl_onehot: lbl_onehot.clone(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let path = std::env::temp_dir().join("test_metrics_logger.csv");
+        let path = path.to_str().unwrap();
+
+        learn_with_logger(path, LogFormat::Csv);
+
+        let content = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+
+        // Header, 4 iterations and 2 evaluations
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(lines.iter().filter(|l| l.starts_with("iteration,")).count(), 4);
+        assert_eq!(lines.iter().filter(|l| l.starts_with("evaluation,")).count(), 2);
+        for line in &lines {
+            assert_eq!(line.split(',').count(), COLUMNS.len());
+        }
+
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(fields[1], "0");
+        assert!(fields[2].parse::<f64>().is_ok());
+        // No scheduler and no validation values
+        assert_eq!(fields[5], "");
+        assert_eq!(fields[7], "");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let path = std::env::temp_dir().join("test_metrics_logger.jsonl");
+        let path = path.to_str().unwrap();
+
+        learn_with_logger(path, LogFormat::JsonLines);
+
+        let content = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("{\"event\":\"iteration\",\"iteration\":0,\"train_loss\":"));
+        assert!(lines[0].contains("\"learning_rate_factor\":null"));
+        assert!(lines[0].ends_with("}"));
+        assert_eq!(lines.iter().filter(|l| l.starts_with("{\"event\":\"evaluation\",\"iteration\":1,")).count(), 1);
+        assert!(lines.iter().any(|l| l.contains("\"val_loss\":0.") || l.contains("\"val_loss\":1.")));
+    }
+
+    #[test]
+    fn test_json_non_finite() {
+        let record = MetricsRecord {
+            event: "iteration",
+            iteration: 3,
+            train_loss: Some(f64::NAN),
+            loss: Some(f64::INFINITY),
+            correct_rate: Some(0.5),
+            learning_rate_factor: None,
+            gradient_norm: None,
+            val_loss: None,
+            val_correct_rate: None,
+            elapsed_seconds: 1.5,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            "{\"event\":\"iteration\",\"iteration\":3,\"train_loss\":null,\"loss\":null,\"correct_rate\":0.5,\"learning_rate_factor\":null,\"gradient_norm\":null,\"val_loss\":null,\"val_correct_rate\":null,\"elapsed_seconds\":1.5}"
+        );
+        assert_eq!(record.to_csv(), "iteration,3,NaN,inf,0.5,,,,,1.5");
+    }
+}
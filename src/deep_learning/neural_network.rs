@@ -1,28 +1,254 @@
 use std::fs::File;
 use std::fs;
+use std::time::Instant;
 use ndarray::prelude::{
     Axis,
     Array2,
 };
+use rand::{
+    Rng,
+    SeedableRng,
+    distributions::{
+        Distribution,
+        WeightedIndex,
+    },
+    rngs::StdRng,
+    seq::SliceRandom,
+};
 
+use crate::deep_learning::callback::*;
+use crate::deep_learning::checkpoint::*;
 use crate::deep_learning::common::*;
+use crate::deep_learning::data_parallel::*;
+use crate::deep_learning::early_stopping::*;
+use crate::deep_learning::history::*;
 use crate::deep_learning::layer::*;
-use crate::deep_learning::graph_plotter::*;
+use crate::deep_learning::metrics::*;
+use crate::deep_learning::optimizer::*;
+use crate::deep_learning::scheduler::*;
+use crate::deep_learning::summary::*;
 
 pub struct LearningParameter {
     pub batch_size: usize,
+    // Used by MinibatchSampling except Epoch
     pub iterations_num: u32,
+    pub sampling: MinibatchSampling,
+    pub gradient_clipping: Option<GradientClipping>,
+    pub scheduler: Option<Box<dyn LearningRateScheduler>>,
+    // Seed of minibatch sampling. Random if None
+    pub seed: Option<u64>,
+    pub checkpoint: Option<CheckpointParameter>,
+    pub validation: Option<ValidationParameter>,
+    pub callbacks: Vec<Box<dyn TrainingCallback>>,
+    // Split each minibatch into shards computed on threads. 1 for single thread
+    pub threads_num: usize,
+}
+
+pub struct ValidationParameter {
+    // Evaluate validation data every `interval` iterations
+    pub interval: u32,
+    pub early_stopping: Option<EarlyStopping>,
+    // Restore the weights of the best validation loss at the end of learning
+    pub restore_best_weights: bool,
+}
+
+pub enum MinibatchSampling {
+    // Choose random rows with replacement every iteration
+    WithReplacement,
+    // Shuffle training data every epoch and walk it in disjoint minibatches
+    Epoch {
+        epochs_num: u32,
+        last_batch: LastBatch,
+    },
+    // Same number of rows from each class of the training labels every iteration (with replacement)
+    ClassBalanced,
+    // Weight of each row is the inverse of its class frequency in the training labels
+    InverseClassFrequency,
+    // Choose rows with probability proportional to the weights made from the training labels (with replacement)
+    Weighted(fn(&Array2<f64>) -> Vec<f64>),
+}
+
+// Rows of each class. Classes without rows are removed because they can not be sampled.
+fn class_indexes(trn_lbl_onehot: &Array2<f64>) -> Vec<Vec<usize>> {
+    let mut class_indexes = vec![Vec::<usize>::new(); trn_lbl_onehot.shape()[1]];
+    for (row_i, row) in trn_lbl_onehot.axis_iter(Axis(0)).enumerate() {
+        class_indexes[max_index_in_arr1(&row.to_owned())].push(row_i);
+    }
+    class_indexes.retain(|indexes| !indexes.is_empty());
+    return class_indexes;
+}
+
+pub fn inverse_class_frequency_weights(trn_lbl_onehot: &Array2<f64>) -> Vec<f64> {
+    let classes: Vec<usize> = trn_lbl_onehot.axis_iter(Axis(0))
+        .map(|row| max_index_in_arr1(&row.to_owned()))
+        .collect();
+    let mut class_counts = vec![0usize; trn_lbl_onehot.shape()[1]];
+    for class in &classes {
+        class_counts[*class] += 1;
+    }
+    return classes.iter().map(|class| 1.0 / class_counts[*class] as f64).collect();
+}
+
+impl ValidationParameter {
+    // Return true if the loss improves the best one.
+    // Same rule as early stopping (min_delta) so the restored weights are the best point it measured.
+    fn observe_loss(&mut self, val_loss: f64, best_val_loss: Option<f64>) -> bool {
+        match self.early_stopping.as_mut() {
+            Some(early_stopping) => early_stopping.observe_loss(val_loss),
+            None => best_val_loss.map_or(true, |best| val_loss < best),
+        }
+    }
+}
+
+// Handling of the final partial minibatch of an epoch
+#[derive(Clone, Copy)]
+pub enum LastBatch {
+    Drop,
+    Keep,
+}
+
+impl LearningParameter {
+    pub fn iterations_per_epoch(&self, data_num: usize) -> u32 {
+        match self.sampling {
+            MinibatchSampling::Epoch { last_batch: LastBatch::Drop, .. } => (data_num / self.batch_size) as u32,
+            MinibatchSampling::Epoch { last_batch: LastBatch::Keep, .. } => ((data_num + self.batch_size - 1) / self.batch_size) as u32,
+            _ => self.iterations_num,
+        }
+    }
+
+    pub fn total_iterations_num(&self, data_num: usize) -> u32 {
+        match self.sampling {
+            MinibatchSampling::Epoch { epochs_num, .. } => epochs_num * self.iterations_per_epoch(data_num),
+            _ => self.iterations_num,
+        }
+    }
+
+    // Rows and weights of the sampling are made from the training labels once before learning
+    pub fn minibatch_sampler(&self, trn_lbl_onehot: &Array2<f64>) -> MinibatchSampler {
+        let data_num = trn_lbl_onehot.shape()[0];
+        let rows = match self.sampling {
+            MinibatchSampling::WithReplacement => SamplerRows::All,
+            MinibatchSampling::Epoch { .. } => {
+                if self.iterations_per_epoch(data_num) == 0 {
+                    panic!("No minibatch in an epoch. data_num: {} batch_size: {}", data_num, self.batch_size);
+                }
+                SamplerRows::Shuffled { epoch: None, indexes: Vec::new() }
+            },
+            MinibatchSampling::ClassBalanced => SamplerRows::Classes(class_indexes(trn_lbl_onehot)),
+            MinibatchSampling::InverseClassFrequency => {
+                SamplerRows::Weights(WeightedIndex::new(inverse_class_frequency_weights(trn_lbl_onehot)).expect("Invalid sampling weights"))
+            },
+            MinibatchSampling::Weighted(make_weights) => {
+                let weights = make_weights(trn_lbl_onehot);
+                if weights.len() != data_num {
+                    panic!("Different data num. weights: {} data_num: {}", weights.len(), data_num);
+                }
+                SamplerRows::Weights(WeightedIndex::new(weights).expect("Invalid sampling weights"))
+            },
+        };
+        MinibatchSampler {
+            batch_size: self.batch_size,
+            data_num: data_num,
+            iterations_per_epoch: self.iterations_per_epoch(data_num),
+            rows: rows,
+        }
+    }
 }
 
+enum SamplerRows {
+    All,
+    // Permutation of the rows for the epoch identified by seed and epoch number
+    Shuffled { epoch: Option<(u64, u32)>, indexes: Vec<usize> },
+    Classes(Vec<Vec<usize>>),
+    Weights(WeightedIndex<f64>),
+}
+
+// Minibatch rows of the training data made by LearningParameter::minibatch_sampler
+pub struct MinibatchSampler {
+    batch_size: usize,
+    data_num: usize,
+    iterations_per_epoch: u32,
+    rows: SamplerRows,
+}
+impl MinibatchSampler {
+    // Training data rows of the minibatch for the iteration
+    pub fn indexes(&mut self, seed: u64, iteration: u32) -> Vec<usize> {
+        let mut rng = seeded_rng(seed, iteration as u64);
+        match self.rows {
+            SamplerRows::All => {
+                random_choice_with_rng(self.batch_size, self.data_num, &mut rng)
+            },
+            SamplerRows::Shuffled { ref mut epoch, ref mut indexes } => {
+                let current_epoch = iteration / self.iterations_per_epoch;
+                let batch_i = (iteration % self.iterations_per_epoch) as usize;
+
+                // Shuffle once an epoch. Same order when resumed in the middle of the epoch.
+                if *epoch != Some((seed, current_epoch)) {
+                    *indexes = (0..self.data_num).collect();
+                    indexes.shuffle(&mut seeded_rng(seed, current_epoch as u64));
+                    *epoch = Some((seed, current_epoch));
+                }
+
+                let start = batch_i * self.batch_size;
+                let end = (start + self.batch_size).min(self.data_num);
+                indexes[start..end].to_vec()
+            },
+            SamplerRows::Classes(ref class_indexes) => {
+                let class_num = class_indexes.len();
+
+                // Remainder rows go to random classes
+                let mut classes: Vec<usize> = (0..self.batch_size).map(|i| i % class_num).collect();
+                let remainder = self.batch_size % class_num;
+                if remainder > 0 {
+                    let mut extra_classes: Vec<usize> = (0..class_num).collect();
+                    extra_classes.shuffle(&mut rng);
+                    let full_num = self.batch_size - remainder;
+                    classes[full_num..].copy_from_slice(&extra_classes[..remainder]);
+                }
+
+                classes.iter().map(|class| {
+                    let indexes = &class_indexes[*class];
+                    indexes[rng.gen_range(0..indexes.len())]
+                }).collect()
+            },
+            SamplerRows::Weights(ref distribution) => {
+                (0..self.batch_size).map(|_| distribution.sample(&mut rng)).collect()
+            },
+        }
+    }
+}
+
+pub enum GradientClipping {
+    // Clip each gradient element into [-max, max]
+    Value(f64),
+    // Rescale all gradients when their global L2 norm exceeds max
+    GlobalNorm(f64),
+}
+
+#[derive(Clone)]
 pub struct LearningResource {
     pub trn_data: Array2<f64>,
     pub trn_lbl_onehot: Array2<f64>,
     pub tst_data: Array2<f64>,
     pub tst_lbl_onehot: Array2<f64>,
+    // Used when LearningParameter::validation is set
+    pub val_data: Array2<f64>,
+    pub val_lbl_onehot: Array2<f64>,
+}
+
+pub struct EvaluationResult {
+    // Average loss per sample
+    pub loss: f64,
+    pub correct_rate: f64,
+    pub sample_num: usize,
+    // Confusion matrix, precision, recall, F1 and top-k accuracy
+    pub metrics: ClassificationMetrics,
 }
 
 pub struct NeuralNetwork {
     last_layer: Box::<dyn NetworkLayer>,
+    // Suppress progress output of learn and test
+    quiet: bool,
 }
 
 impl NeuralNetwork {
@@ -31,6 +257,18 @@ impl NeuralNetwork {
     {
         NeuralNetwork {
             last_layer: Box::new(last_layer),
+            quiet: false,
+        }
+    }
+
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+    pub fn is_quiet(&self) -> bool {self.quiet}
+
+    pub(crate) fn print_progress(&self, message: String) {
+        if !self.quiet {
+            println!("{}", message);
         }
     }
     pub fn set_input(&mut self, input: &Array2<f64>) {
@@ -49,82 +287,433 @@ impl NeuralNetwork {
         return self.last_layer;
     }
 
-    pub fn learn(&mut self, parameter: LearningParameter, resource: LearningResource) {
-        let mut correct_rates = Vec::<f64>::with_capacity((parameter.iterations_num + 1) as usize);
-        let mut losses = Vec::<f64>::with_capacity((parameter.iterations_num + 1) as usize);
+    // Stop updating every parameter while keeping gradient propagation
+    pub fn freeze(&mut self) {
+        self.last_layer.freeze_except_top(0);
+    }
+
+    pub fn unfreeze(&mut self) {
+        self.last_layer.freeze_except_top(usize::MAX);
+    }
+
+    // Fine-tune only the `trainable_num` layers with parameters closest to the output
+    pub fn freeze_except_top(&mut self, trainable_num: usize) {
+        self.last_layer.freeze_except_top(trainable_num);
+    }
+
+    // Call visitor with every parameter and its name. e.g. "1.affine/w"
+    pub fn visit_parameters<F>(&mut self, mut visitor: F)
+        where F: FnMut(&str, &mut dyn Parameter)
+    {
+        self.last_layer.visit_parameters(&mut visitor);
+    }
 
-        println!("Start learning");
-        let (loss, rate) = self.test(parameter.batch_size, &resource.tst_data, &resource.tst_lbl_onehot);
-        correct_rates.push(rate);
-        losses.push(loss);
+    pub fn zero_grad(&mut self) {
+        self.visit_parameters(|_, parameter| {
+            parameter.zero_grad();
+        });
+    }
+
+    // Forward and backward the batch and accumulate gradients into each parameter.
+    // Return the mean loss of the forward pass which produced the gradients.
+    pub fn compute_gradient(&mut self, batch_data: &Array2<f64>, batch_lbl_onehot: &Array2<f64>) -> f64 {
+        self.set_input(batch_data);
+        self.set_lbl(batch_lbl_onehot);
+
+        let loss = self.last_layer.forward(true);
+        let init_dout = Array2::<f64>::ones(loss.dim());
+        self.last_layer.backward(init_dout);
+        return loss.mean().unwrap_or(0f64);
+    }
+
+    // Global L2 norm of the gradients of trainable parameters
+    pub fn gradient_norm(&mut self) -> f64 {
+        let mut squared_sum = 0f64;
+        self.visit_parameters(|_, parameter| {
+            if !parameter.is_trainable() {
+                return;
+            }
+            if let Some(gradient) = parameter.get_gradient() {
+                squared_sum += (gradient * gradient).sum();
+            }
+        });
+        return squared_sum.sqrt();
+    }
+
+    // Clip gradients of trainable parameters and return the norm before clipping
+    pub fn clip_gradient(&mut self, clipping: &GradientClipping) -> f64 {
+        let norm = self.gradient_norm();
+        match *clipping {
+            GradientClipping::Value(max) => {
+                self.visit_parameters(|_, parameter| {
+                    if !parameter.is_trainable() {
+                        return;
+                    }
+                    if let Some(gradient) = parameter.get_gradient_mut() {
+                        gradient.mapv_inplace(|g: f64| -> f64 {g.max(-max).min(max)});
+                    }
+                });
+            },
+            GradientClipping::GlobalNorm(max_norm) => {
+                if norm > max_norm {
+                    let scale = max_norm / norm;
+                    self.visit_parameters(|_, parameter| {
+                        if !parameter.is_trainable() {
+                            return;
+                        }
+                        if let Some(gradient) = parameter.get_gradient_mut() {
+                            *gradient *= scale;
+                        }
+                    });
+                }
+            },
+        }
+        return norm;
+    }
+
+    // Update each parameter with the accumulated gradient by its optimizer
+    pub fn step(&mut self) {
+        self.visit_parameters(|_, parameter| {
+            parameter.step();
+        });
+    }
+
+    // Copy of every parameter value in visit_parameters order
+    pub fn parameter_values(&mut self) -> Vec<Array2<f64>> {
+        let mut values = Vec::<Array2<f64>>::new();
+        self.visit_parameters(|_, parameter| {
+            values.push(parameter.get_value().clone());
+        });
+        return values;
+    }
+
+    pub fn set_parameter_values(&mut self, values: &[Array2<f64>]) {
+        let mut i = 0;
+        self.visit_parameters(|_, parameter| {
+            parameter.get_value_mut().assign(&values[i]);
+            i += 1;
+        });
+    }
+
+    // Learning rates of the optimizers of every parameter in visit_parameters order
+    pub fn learning_rates(&mut self) -> Vec<f64> {
+        let mut learning_rates = Vec::<f64>::new();
+        self.visit_parameters(|_, parameter| {
+            learning_rates.push(parameter.get_learning_rate());
+        });
+        return learning_rates;
+    }
+
+    // Set each learning rate to base learning rate * factor
+    pub fn scale_learning_rates(&mut self, base_learning_rates: &[f64], factor: f64) {
+        let mut i = 0;
+        self.visit_parameters(|_, parameter| {
+            parameter.set_learning_rate(base_learning_rates[i] * factor);
+            i += 1;
+        });
+    }
+
+    // Optimizers with their state (e.g. momentum) in visit_parameters order
+    #[cfg (not (target_family = "wasm"))]
+    pub fn optimizer_states(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut buffers = Vec::<Vec<u8>>::new();
+        let mut result = Ok(());
+        self.visit_parameters(|_, parameter| {
+            let mut buffer = Vec::<u8>::new();
+            if let Err(e) = parameter.get_optimizer().export(&mut buffer) {
+                result = Err(e);
+            }
+            buffers.push(buffer);
+        });
+        result?;
+
+        let mut states = Vec::<String>::new();
+        for buffer in buffers {
+            states.push(String::from_utf8(buffer)?);
+        }
+        return Ok(states);
+    }
+
+    // Replace the optimizers with ones imported from optimizer_states
+    pub fn set_optimizer_states(&mut self, states: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut optimizers = Vec::<Box<dyn Optimizer>>::new();
+        for state in states {
+            optimizers.push(import_optimizer(&mut state.lines())?);
+        }
+        let mut parameters_num = 0;
+        self.visit_parameters(|_, _| parameters_num += 1);
+        if parameters_num != optimizers.len() {
+            return Err(format!("Optimizer states count {} does not match parameters count {}", optimizers.len(), parameters_num).into());
+        }
+        let mut optimizers = optimizers.into_iter();
+        self.visit_parameters(|_, parameter| {
+            parameter.set_optimizer(optimizers.next().unwrap());
+        });
+        return Ok(());
+    }
+
+    // Layer types, output shapes and parameter counts like Keras' summary
+    pub fn summary(&mut self) -> NetworkSummary {
+        let mut layers = Vec::<LayerSummary>::new();
+        self.last_layer.summarize(&mut layers);
+        NetworkSummary {
+            layers: layers,
+        }
+    }
+
+    pub fn learn(&mut self, parameter: LearningParameter, resource: LearningResource) -> TrainingHistory {
+        self.print_progress(format!("Start learning"));
+        let (loss, rate) = self.test_iteration(&parameter, &resource);
         // self.last_layer.plot();
 
-        for iteration in 0..parameter.iterations_num {
+        let checkpoint = Checkpoint {
+            iteration: 0,
+            seed: parameter.seed.unwrap_or_else(|| rand::random::<u64>()),
+            base_learning_rates: self.learning_rates(),
+            losses: vec![loss],
+            correct_rates: vec![rate],
+            val_losses: Vec::new(),
+            val_correct_rates: Vec::new(),
+        };
+        return self.learn_from_checkpoint(parameter, resource, checkpoint);
+    }
+
+    // Continue learning of a network imported by import_checkpoint
+    // History contains the iterations after the checkpoint
+    pub fn resume_learning(&mut self, parameter: LearningParameter, resource: LearningResource, checkpoint: Checkpoint) -> TrainingHistory {
+        self.print_progress(format!("Resume learning from iteration:{}", checkpoint.iteration));
+        return self.learn_from_checkpoint(parameter, resource, checkpoint);
+    }
+
+    fn learn_from_checkpoint(&mut self, mut parameter: LearningParameter, resource: LearningResource, mut checkpoint: Checkpoint) -> TrainingHistory {
+        let start_time = Instant::now();
+        let mut history = TrainingHistory::new();
+
+        // Restore state of the scheduler by the losses observed before the checkpoint
+        if let Some(scheduler) = parameter.scheduler.as_mut() {
+            for loss in checkpoint.losses.iter().skip(1) {
+                scheduler.observe_loss(*loss);
+            }
+        }
+        // Weights before the checkpoint are not saved.
+        // So the best weights are restored only if validation loss improves after resuming.
+        let mut best_val_loss = None;
+        let mut best_values = None;
+        if let Some(validation) = parameter.validation.as_mut() {
+            for val_loss in &checkpoint.val_losses {
+                if validation.observe_loss(*val_loss, best_val_loss) {
+                    best_val_loss = Some(*val_loss);
+                }
+            }
+        }
+
+        for callback in parameter.callbacks.iter_mut() {
+            callback.on_train_begin(self, &checkpoint);
+        }
+
+        let mut data_parallel = None;
+        if parameter.threads_num > 1 {
+            match DataParallel::new(self, parameter.threads_num) {
+                Ok(v) => data_parallel = Some(v),
+                Err(e) => panic!("Can not learn with {} threads. {}", parameter.threads_num, e),
+            }
+        }
+
+        let data_num = resource.trn_data.shape()[0];
+        let iterations_num = parameter.total_iterations_num(data_num);
+        let iterations_per_epoch = parameter.iterations_per_epoch(data_num);
+        let mut sampler = parameter.minibatch_sampler(&resource.trn_lbl_onehot);
+        for iteration in checkpoint.iteration..iterations_num {
+            let mut learning_rate_factor = None;
+            if let Some(scheduler) = parameter.scheduler.as_mut() {
+                let factor = scheduler.factor(iteration);
+                self.scale_learning_rates(&checkpoint.base_learning_rates, factor);
+                self.print_progress(format!("Learning rate factor: {}", factor));
+                learning_rate_factor = Some(factor);
+            }
+
             // Choise batch data
+            let indexes = sampler.indexes(checkpoint.seed, iteration);
             let (batch_data, batch_lbl_onehot) = 
-                make_minibatch_data(parameter.batch_size, &resource.trn_data, &resource.trn_lbl_onehot);
+                make_minibatch_data_from_indexes(&indexes, &resource.trn_data, &resource.trn_lbl_onehot);
      
-            // // Set batch data
-            self.set_input(&batch_data);
-            self.set_lbl(&batch_lbl_onehot);
-
             // Update value weight and bias
-            let init_dout = Array2::<f64>::ones(
-                (
-                    self.last_layer.forward(true).shape()[0],
-                    self.last_layer.forward(true).shape()[1],
-                )
-            );
-            self.last_layer.backward(init_dout);
+            let train_loss = match data_parallel.as_mut() {
+                Some(data_parallel) => data_parallel.compute_gradient(self, &batch_data, &batch_lbl_onehot),
+                None => {
+                    self.zero_grad();
+                    self.compute_gradient(&batch_data, &batch_lbl_onehot)
+                },
+            };
+            let mut gradient_norm = None;
+            if let Some(clipping) = parameter.gradient_clipping.as_ref() {
+                let norm = self.clip_gradient(clipping);
+                self.print_progress(format!("Gradient norm: {}", norm));
+                gradient_norm = Some(norm);
+            }
+            self.step();
 
-            println!("Complete iteratioin:{}", iteration);
-            let (loss, rate) = self.test(parameter.batch_size, &resource.tst_data, &resource.tst_lbl_onehot);
-            correct_rates.push(rate);
-            losses.push(loss);
-        }
-        plot_rate(correct_rates, "correct_rate");
-        plot_loss(losses, "loss");
-        self.last_layer.plot();
-    }
+            self.print_progress(format!("Complete iteratioin:{}", iteration));
+            if let MinibatchSampling::Epoch { .. } = parameter.sampling {
+                if (iteration + 1) % iterations_per_epoch == 0 {
+                    self.print_progress(format!("Complete epoch:{}", iteration / iterations_per_epoch));
+                }
+            }
+            let (loss, rate) = self.test_iteration(&parameter, &resource);
+            checkpoint.correct_rates.push(rate);
+            checkpoint.losses.push(loss);
+            checkpoint.iteration = iteration + 1;
+            if let Some(scheduler) = parameter.scheduler.as_mut() {
+                scheduler.observe_loss(loss);
+            }
 
-    pub fn test(&mut self, batch_size: usize, tst_data: &Array2<f64>, tst_lbl_onehot: &Array2<f64>) -> (f64, f64){
-        let mut correct_rate = 0.0;
-        let mut loss = 0.0;
-        for _ in 0..10 {
-            // Choise batch data
-            let (batch_data, batch_lbl_onehot) = make_minibatch_data(batch_size, &tst_data, &tst_lbl_onehot);
+            history.iterations.push(IterationRecord {
+                iteration: iteration,
+                train_loss: train_loss,
+                test_loss: loss,
+                test_correct_rate: rate,
+                learning_rates: self.learning_rates(),
+                elapsed_seconds: start_time.elapsed().as_secs_f64(),
+            });
 
-            // Set batch data
-            self.set_input(&batch_data);
-            self.set_lbl(&batch_lbl_onehot);
+            let mut stop = false;
+            let metrics = IterationMetrics {
+                iteration: iteration,
+                train_loss: train_loss,
+                loss: loss,
+                correct_rate: rate,
+                learning_rate_factor: learning_rate_factor,
+                gradient_norm: gradient_norm,
+            };
+            for callback in parameter.callbacks.iter_mut() {
+                stop |= callback.on_iteration_end(self, &metrics);
+            }
 
-            // Forward (skip loss)        
-            let test_res = self.last_layer.forward_skip_loss(false);
+            if let Some(validation) = parameter.validation.as_mut() {
+                if validation.interval > 0 && checkpoint.iteration % validation.interval == 0 {
+                    let result = self.evaluate(parameter.batch_size, &resource.val_data, &resource.val_lbl_onehot);
+                    let (val_loss, val_rate) = (result.loss, result.correct_rate);
+                    self.print_progress(format!("Validation Loss: {}", val_loss));
+                    self.print_progress(format!("Validation CorrectRate: {}%", val_rate * 100.0));
+                    checkpoint.val_losses.push(val_loss);
+                    checkpoint.val_correct_rates.push(val_rate);
+                    history.evaluations.push(EvaluationRecord {
+                        iteration: iteration,
+                        val_loss: val_loss,
+                        val_correct_rate: val_rate,
+                    });
 
-            correct_rate += calc_correct_rate(&test_res, &batch_lbl_onehot) / 10f64;
+                    if validation.observe_loss(val_loss, best_val_loss) {
+                        best_val_loss = Some(val_loss);
+                        if validation.restore_best_weights {
+                            best_values = Some(self.parameter_values());
+                        }
+                    }
+                    if let Some(early_stopping) = validation.early_stopping.as_ref() {
+                        if early_stopping.should_stop() {
+                            self.print_progress(format!("Early stopping at iteration:{}", iteration));
+                            stop = true;
+                        }
+                    }
 
-            // Forwards
-            let batch_loss = self.forward(false);
+                    let metrics = EvaluationMetrics {
+                        iteration: iteration,
+                        val_loss: val_loss,
+                        val_correct_rate: val_rate,
+                    };
+                    for callback in parameter.callbacks.iter_mut() {
+                        stop |= callback.on_evaluation(self, &metrics);
+                    }
+                }
+            }
 
-            // calc loss
-            let mut loss_sum = 0f64;
-            for l in &batch_loss {
-                loss_sum += *l;
+            #[cfg (not (target_family = "wasm"))]
+            if let Some(checkpoint_parameter) = parameter.checkpoint.as_ref() {
+                if checkpoint_parameter.interval > 0 && checkpoint.iteration % checkpoint_parameter.interval == 0 {
+                    match self.export_checkpoint(&checkpoint_parameter.file_path, &checkpoint) {
+                        Ok(()) => self.print_progress(format!("Save checkpoint: {}", checkpoint_parameter.file_path)),
+                        Err(e) => self.print_progress(format!("Failed to save checkpoint: {}", e)),
+                    }
+                }
+            }
+
+            if stop {
+                break;
             }
-            loss += loss_sum / batch_loss.len() as f64 / 10f64;
         }
-        
-        println!("Test Loss: {}", loss);
-        println!("Test CorrectRate: {}%", correct_rate * 100.0);
-        println!("");
+        if let Some(best_values) = best_values {
+            self.print_progress(format!("Restore best weights. Validation Loss: {}", best_val_loss.unwrap()));
+            self.set_parameter_values(&best_values);
+        }
+        for callback in parameter.callbacks.iter_mut() {
+            callback.on_train_end(self, &checkpoint);
+        }
+        return history;
+    }
+
+    // Loss and correct rate of the whole test data
+    // Loss and correct rate of the test data after an iteration.
+    // Deterministic loss of the whole test data only when the scheduler reacts to it, so it does not react to sampling noise.
+    // Otherwise random minibatches are enough to show the progress.
+    fn test_iteration(&mut self, parameter: &LearningParameter, resource: &LearningResource) -> (f64, f64) {
+        if parameter.scheduler.as_ref().map_or(false, |scheduler| scheduler.observes_loss()) {
+            return self.evaluate_test_data(parameter.batch_size, resource);
+        }
+        return self.test(parameter.batch_size, &resource.tst_data, &resource.tst_lbl_onehot);
+    }
+
+    fn evaluate_test_data(&mut self, batch_size: usize, resource: &LearningResource) -> (f64, f64) {
+        let result = self.evaluate(batch_size, &resource.tst_data, &resource.tst_lbl_onehot);
+
+        self.print_progress(format!("Test Loss: {}", result.loss));
+        self.print_progress(format!("Test CorrectRate: {}%", result.correct_rate * 100.0));
+        self.print_progress(format!(""));
+
+        return (result.loss, result.correct_rate);
+    }
+
+    // Plot histograms of parameters
+    pub fn plot(&self) {
+        self.last_layer.plot();
+    }
+
+    pub fn test(&mut self, batch_size: usize, tst_data: &Array2<f64>, tst_lbl_onehot: &Array2<f64>) -> (f64, f64){
+        let quiet = self.quiet;
+        return test_minibatches(batch_size, tst_data, tst_lbl_onehot, quiet, |batch_data, batch_lbl_onehot| {
+            self.set_input(batch_data);
+            self.set_lbl(batch_lbl_onehot);
+            (self.last_layer.forward_skip_loss(false), self.forward(false))
+        });
+    }
+
+    // Walk the whole data in order. The last batch can be smaller than batch_size.
+    pub fn evaluate(&mut self, batch_size: usize, data: &Array2<f64>, lbl_onehot: &Array2<f64>) -> EvaluationResult {
+        return evaluate_minibatches(batch_size, data, lbl_onehot, |batch_data, batch_lbl_onehot| {
+            self.set_input(batch_data);
+            self.set_lbl(batch_lbl_onehot);
+            (self.last_layer.forward_skip_loss(false), self.forward(false))
+        });
+    }
+
+    pub fn import_from_file(file_path: &str) -> Result<NeuralNetwork, Box<dyn std::error::Error>> {
+        let data_string = fs::read_to_string(file_path)?;
+        let layer = import_network_layer(&mut data_string.lines())?;
 
-        return (loss, correct_rate);
+        Ok(NeuralNetwork { last_layer: layer, quiet: false })
     }
 
-    pub fn import_from_file(file_path: &str) -> Result<NeuralNetwork, Box<std::error::Error>> {
+    pub fn import_checkpoint(file_path: &str) -> Result<(NeuralNetwork, Checkpoint), Box<dyn std::error::Error>> {
         let data_string = fs::read_to_string(file_path)?;
-        
-        Ok(Self::import(data_string.as_str()))
+        let mut lines = data_string.lines();
+
+        let label = lines.next().unwrap_or("");
+        if label != Checkpoint::checkpoint_label() {
+            return Err(format!("No match checkpoint label '{}'", label).into());
+        }
+        let checkpoint = Checkpoint::import(&mut lines)?;
+        let layer = import_network_layer(&mut lines)?;
+
+        Ok((NeuralNetwork { last_layer: layer, quiet: false }, checkpoint))
     }
 
     pub fn guess(&mut self, batch_data: &Array2<f64>) -> Array2<f64> {
@@ -136,16 +725,17 @@ impl NeuralNetwork {
     pub fn import(data: &str) -> Self {
         let mut lines = data.lines();
 
-        let layer = import_network_layer(&mut lines);
+        let layer = import_network_layer(&mut lines).expect("Failed to import the network");
 
         NeuralNetwork {
             last_layer: layer,
+            quiet: false,
         }
 
     }
 
     #[cfg (not (target_family = "wasm"))]
-    pub fn export(&self) -> Result<(), Box<std::error::Error>> {
+    pub fn export(&self) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = "./nn.csv";
         // let mut file = match File::open(file_path) {
         //     Err(why) => File::create(file_path)?,
@@ -157,43 +747,98 @@ impl NeuralNetwork {
 
         return Ok(())
     }
+
+    // Network without layers used only in learning (e.g. distillation teacher)
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export_inference(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = File::create(file_path)?;
+
+        self.last_layer.export_inference(&mut file)?;
+
+        return Ok(())
+    }
+
+    // Same format as export. Used to copy the network
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export_to_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut buffer = Vec::<u8>::new();
+        self.last_layer.export(&mut buffer)?;
+
+        return Ok(String::from_utf8(buffer)?)
+    }
+
+    // Same format as export_inference
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export_inference_to_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut buffer = Vec::<u8>::new();
+        self.last_layer.export_inference(&mut buffer)?;
+
+        return Ok(String::from_utf8(buffer)?)
+    }
+
+    // Write learning progress followed by the layers with optimizer state.
+    // Written to a temporary file first so the previous checkpoint survives a crash while writing.
+    #[cfg (not (target_family = "wasm"))]
+    pub fn export_checkpoint(&self, file_path: &str, checkpoint: &Checkpoint) -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_file_path = format!("{}.tmp", file_path);
+        let mut file = File::create(&tmp_file_path)?;
+
+        checkpoint.export(&mut file)?;
+        self.last_layer.export(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&tmp_file_path, file_path)?;
+
+        return Ok(())
+    }
+}
+
+// Rng of minibatch sampling for the iteration (or epoch).
+// Seeded by the index so resumed learning samples the same minibatches.
+fn seeded_rng(seed: u64, index: u64) -> StdRng {
+    StdRng::seed_from_u64(seed.wrapping_add(index))
 }
 
-pub fn import_network_layer<'a, T>(lines: &mut T) -> Box<dyn NetworkLayer>
+pub fn import_network_layer<'a, T>(lines: &mut T) -> Result<Box<dyn NetworkLayer>, Box<dyn std::error::Error>>
     where T: Iterator<Item = &'a str>
 {
-    let layer_label = lines.next().unwrap();
+    let layer_label = next_import_value(lines)?;
 
     let layer: Box<dyn NetworkLayer> = 
-    if layer_label == AffineDirectValue::layer_label() {
-        Box::new(AffineDirectValue::import(lines))
+    if layer_label == AffineDirectValue::layer_label_v2() {
+        Box::new(AffineDirectValue::import(lines)?)
+    } else if layer_label == AffineDirectValue::layer_label() {
+        Box::new(AffineDirectValue::import_legacy(lines)?)
     } else if layer_label == Affine::layer_label() {
-        Box::new(Affine::import(lines))
+        Box::new(Affine::import(lines)?)
     } else if layer_label == BatchNorm::layer_label() {
-        Box::new(BatchNorm::import(lines))
+        Box::new(BatchNorm::import(lines)?)
+    } else if layer_label == NetworkBatchNormValueLayer::layer_label() {
+        Box::new(NetworkBatchNormValueLayer::import(lines)?)
     } else if layer_label == Convolution::layer_label() {
-        Box::new(Convolution::import(lines))
+        Box::new(Convolution::import(lines)?)
     } else if layer_label == DirectValue::layer_label() {
-        Box::new(DirectValue::import(lines))
+        Box::new(DirectValue::import(lines)?)
+    } else if layer_label == DistillationWithLoss::layer_label() {
+        Box::new(DistillationWithLoss::import(lines)?)
     } else if layer_label == Dropout::layer_label() {
-        Box::new(Dropout::import(lines))
+        Box::new(Dropout::import(lines)?)
     } else if layer_label == Pooling::layer_label() {
-        Box::new(Pooling::import(lines))
+        Box::new(Pooling::import(lines)?)
     } else if layer_label == Relu::layer_label() {
-        Box::new(Relu::import(lines))
+        Box::new(Relu::import(lines)?)
     } else if layer_label == Sigmoid::layer_label() {
-        Box::new(Sigmoid::import(lines))
+        Box::new(Sigmoid::import(lines)?)
     } else if layer_label == SoftmaxWithLoss::layer_label() {
-        Box::new(SoftmaxWithLoss::import(lines))
+        Box::new(SoftmaxWithLoss::import(lines)?)
     } else {
-        panic!("No match layer label '{}'", layer_label);
+        return Err(format!("No match layer label '{}'", layer_label).into());
     };
 
     // for l in lines {}
-    return layer;
+    return Ok(layer);
 }
 
-// pub fn import_network_layer<T>(lines: &mut Lines<T>) -> Result<Box<dyn NetworkLayer>, Box<std::error::Error>>
+// pub fn import_network_layer<T>(lines: &mut Lines<T>) -> Result<Box<dyn NetworkLayer>, Box<dyn std::error::Error>>
 //     where T: BufRead
 // {
 //     // let layer_label = match(lines.next()) {
@@ -249,12 +894,77 @@ fn calc_correct_rate(result: &Array2<f64>, lbl_onehot: &Array2<f64>) -> f64 {
     return correct_count as f64 / result.shape()[0] as f64;
 }
 
+// Number of random minibatches averaged by test
+const TEST_MINIBATCHES_NUM: usize = 10;
+
+// Mean loss and correct rate of random minibatches. Printed unless quiet.
+// guess returns the output (skip loss) and the loss of each row for the batch data and labels.
+pub fn test_minibatches<F>(batch_size: usize, tst_data: &Array2<f64>, tst_lbl_onehot: &Array2<f64>, quiet: bool, mut guess: F) -> (f64, f64)
+    where F: FnMut(&Array2<f64>, &Array2<f64>) -> (Array2<f64>, Array2<f64>)
+{
+    let mut correct_rate = 0.0;
+    let mut loss = 0.0;
+    for _ in 0..TEST_MINIBATCHES_NUM {
+        // Choose batch data
+        let (batch_data, batch_lbl_onehot) = make_minibatch_data(batch_size, tst_data, tst_lbl_onehot);
+
+        let (res, batch_loss) = guess(&batch_data, &batch_lbl_onehot);
+        correct_rate += calc_correct_rate(&res, &batch_lbl_onehot) / TEST_MINIBATCHES_NUM as f64;
+        loss += batch_loss.mean().unwrap_or(0f64) / TEST_MINIBATCHES_NUM as f64;
+    }
+
+    if !quiet {
+        println!("Test Loss: {}", loss);
+        println!("Test CorrectRate: {}%", correct_rate * 100.0);
+        println!("");
+    }
+
+    return (loss, correct_rate);
+}
+
+// Metrics of the whole data walked in order. The last batch can be smaller than batch_size.
+// guess is the same as test_minibatches.
+pub fn evaluate_minibatches<F>(batch_size: usize, data: &Array2<f64>, lbl_onehot: &Array2<f64>, mut guess: F) -> EvaluationResult
+    where F: FnMut(&Array2<f64>, &Array2<f64>) -> (Array2<f64>, Array2<f64>)
+{
+    let sample_num = data.shape()[0];
+    let mut loss_sum = 0f64;
+    let mut metrics = ClassificationMetrics::new(lbl_onehot.shape()[1]);
+    let mut start = 0;
+    while start < sample_num {
+        let end = (start + batch_size).min(sample_num);
+        let indexes: Vec<usize> = (start..end).collect();
+        let (batch_data, batch_lbl_onehot) = make_minibatch_data_from_indexes(&indexes, data, lbl_onehot);
+
+        let (res, batch_loss) = guess(&batch_data, &batch_lbl_onehot);
+        metrics.add_batch(&res, &batch_lbl_onehot);
+        loss_sum += batch_loss.sum();
+
+        start = end;
+    }
+
+    return EvaluationResult {
+        loss: if sample_num == 0 {0f64} else {loss_sum / sample_num as f64},
+        correct_rate: metrics.accuracy(),
+        sample_num: sample_num,
+        metrics: metrics,
+    };
+}
+
 pub fn make_minibatch_data(minibatch_size: usize, data: &Array2<f64>, lbl_onehot: &Array2<f64>) -> (Array2<f64>, Array2<f64>) {
+    return make_minibatch_data_with_rng(minibatch_size, data, lbl_onehot, &mut rand::thread_rng());
+}
+
+pub fn make_minibatch_data_with_rng<R: Rng>(minibatch_size: usize, data: &Array2<f64>, lbl_onehot: &Array2<f64>, rng: &mut R) -> (Array2<f64>, Array2<f64>) {
+    let indexes = random_choice_with_rng(minibatch_size, data.shape()[0], rng);
+    return make_minibatch_data_from_indexes(&indexes, data, lbl_onehot);
+}
+
+pub fn make_minibatch_data_from_indexes(indexes: &[usize], data: &Array2<f64>, lbl_onehot: &Array2<f64>) -> (Array2<f64>, Array2<f64>) {
+    let minibatch_size = indexes.len();
     let mut minibatch_data = Array2::<f64>::zeros((minibatch_size, data.shape()[1]));
     let mut minibatch_lbl_onehot = Array2::<f64>::zeros((minibatch_size, lbl_onehot.shape()[1]));
 
-    let indexes = random_choice(minibatch_size, data.shape()[0]);
-
     for row_i in 0..minibatch_size {
         let batch_i = indexes[row_i];
 
@@ -327,4 +1037,630 @@ mod test_neuaral_network {
         );
         nn.set_lbl(&lbl);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_visit_parameters() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random_with_name(
+            layers,
+            3,
+            5,
+            Sgd::new(0.01),
+            Sgd::new(0.01),
+            "layer1".to_string(),
+        );
+        let layers = Relu::new(layers);
+        let layers = Affine::new_random(
+            layers,
+            5,
+            4,
+            Sgd::new(0.01),
+            Sgd::new(0.01)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let mut parameters = Vec::<(String, String, Vec<usize>)>::new();
+        nn.visit_parameters(|name, parameter| {
+            parameters.push((name.to_string(), parameter.get_name().to_string(), parameter.get_value().shape().to_vec()));
+        });
+        assert_eq!(parameters, vec![
+            ("1.affine/w".to_string(), "layer1_weight".to_string(), vec![3, 5]),
+            ("1.affine/b".to_string(), "layer1_bias".to_string(), vec![1, 5]),
+            ("3.affine/w".to_string(), "".to_string(), vec![5, 4]),
+            ("3.affine/b".to_string(), "".to_string(), vec![1, 4]),
+        ]);
+
+        // Stacking layers on top keeps the names of the layers below
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(layers, 3, 5, Sgd::new(0.01), Sgd::new(0.01));
+        let layers = Relu::new(layers);
+        let layers = Affine::new_random(layers, 5, 4, Sgd::new(0.01), Sgd::new(0.01));
+        let layers = Relu::new(layers);
+        let layers = Affine::new_random(layers, 4, 2, Sgd::new(0.01), Sgd::new(0.01));
+        let mut stacked = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut names = Vec::<String>::new();
+        let index = stacked.visit_parameters(&mut |name: &str, _: &mut dyn Parameter| names.push(name.to_string()));
+        assert_eq!(index, 6);
+        assert_eq!(names[..4], parameters.iter().map(|p| p.0.clone()).collect::<Vec<String>>()[..]);
+        assert_eq!(names[4..], ["5.affine/w".to_string(), "5.affine/b".to_string()]);
+
+        // Write access
+        nn.visit_parameters(|_, parameter| {
+            parameter.get_value_mut().fill(0f64);
+        });
+        nn.set_input(&arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        ));
+        assert_eq!(nn.guess(&arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        )), Array2::<f64>::zeros((batch_size, 4)));
+    }
+
+    #[test]
+    fn test_summary() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            5,
+            Sgd::new(0.01),
+            Sgd::new(0.01)
+        );
+        let layers = Relu::new(layers);
+        let layers = Affine::new_random(
+            layers,
+            5,
+            4,
+            Sgd::new(0.01),
+            Sgd::new(0.01)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+        nn.freeze_except_top(1);
+
+        let summary = nn.summary();
+
+        let labels: Vec<&str> = summary.layers.iter().map(|l| l.layer_label.as_str()).collect();
+        assert_eq!(labels, vec!["direct", "affine", "relu", "affine", "softmax"]);
+        let output_shapes: Vec<Vec<usize>> = summary.layers.iter().map(|l| l.output_shape.clone()).collect();
+        assert_eq!(output_shapes, vec![vec![2, 3], vec![2, 5], vec![2, 5], vec![2, 4], vec![2, 1]]);
+        assert_eq!(summary.total_parameter_num(), (3 * 5 + 5) + (5 * 4 + 4));
+        assert_eq!(summary.trainable_parameter_num(), 5 * 4 + 4);
+        assert_eq!(summary.parameter_memory_bytes(), summary.total_parameter_num() * 8);
+    }
+
+    #[test]
+    fn test_summary_output_shapes() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::from_shape_fn((batch_size, 1 * 6 * 6), |(i, j)| (i + j) as f64));
+        let layers = Convolution::new_random(layers, Sgd::new(0.01), Sgd::new(0.01), batch_size, 1, 3, 3, 3, 6, 6, 1, 0);
+        let layers = Pooling::new(layers, (batch_size, 3, 4, 4), 2, 2, 2, 0);
+        let layers = BatchNorm::new(
+            layers,
+            NetworkBatchNormValueLayer::new(Array2::<f64>::ones((1, 3 * 2 * 2)), Sgd::new(0.01)),
+            NetworkBatchNormValueLayer::new(Array2::<f64>::zeros((1, 3 * 2 * 2)), Sgd::new(0.01)),
+        );
+        let layers = Affine::new_random(layers, 3 * 2 * 2, 4, Sgd::new(0.01), Sgd::new(0.01));
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let summary = nn.summary();
+
+        let output_shapes: Vec<Vec<usize>> = summary.layers.iter().map(|l| l.output_shape.clone()).collect();
+        assert_eq!(output_shapes, vec![vec![2, 36], vec![2, 48], vec![2, 12], vec![2, 12], vec![2, 4], vec![2, 1]]);
+        assert_eq!(nn.last_layer.forward_skip_loss(false).shape(), &[2, 4]);
+    }
+
+    #[test]
+    fn test_compute_gradient_and_step() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let input = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let lbl = arr2(&
+            [
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]
+        );
+
+        let mut values = Vec::<Array2<f64>>::new();
+        nn.visit_parameters(|_, parameter| {
+            values.push(parameter.get_value().clone());
+        });
+
+        // Gradient accumulated over 2 batches without update
+        nn.zero_grad();
+        nn.compute_gradient(&input, &lbl);
+        let mut gradients = Vec::<Array2<f64>>::new();
+        nn.visit_parameters(|_, parameter| {
+            gradients.push(parameter.get_gradient().unwrap().clone());
+        });
+        nn.compute_gradient(&input, &lbl);
+        let mut i = 0;
+        nn.visit_parameters(|_, parameter| {
+            assert_eq!(parameter.get_value(), &values[i]);
+            assert_eq!(round_digit_arr2(parameter.get_gradient().unwrap(), -8), round_digit_arr2(&(&gradients[i] * 2.0), -8));
+            i += 1;
+        });
+
+        // Update by SGD
+        nn.step();
+        let mut i = 0;
+        nn.visit_parameters(|_, parameter| {
+            assert_eq!(
+                round_digit_arr2(parameter.get_value(), -8),
+                round_digit_arr2(&(&values[i] - &gradients[i] * 2.0 * 0.1), -8)
+            );
+            i += 1;
+        });
+
+        nn.zero_grad();
+        nn.visit_parameters(|_, parameter| {
+            assert_eq!(parameter.get_gradient(), None);
+        });
+    }
+
+    #[test]
+    fn test_scale_learning_rates() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Adam::new(0.01, 0.9, 0.999)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let base_learning_rates = nn.learning_rates();
+        assert_eq!(base_learning_rates, vec![0.1, 0.01]);
+
+        let mut scheduler = StepDecay::new(1, 0.5);
+        nn.scale_learning_rates(&base_learning_rates, scheduler.factor(2));
+        assert_eq!(nn.learning_rates(), vec![0.025, 0.0025]);
+    }
+
+    #[test]
+    fn test_learn_reduce_on_plateau_reproducible() {
+        let batch_size = 2;
+        let data = Array2::<f64>::from_shape_fn((6, 3), |(i, j)| (i + j) as f64 / 10.0);
+        let lbl_onehot = Array2::<f64>::from_shape_fn((6, 2), |(i, j)| if i % 2 == j {1.0} else {0.0});
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(layers, 3, 2, Sgd::new(0.5), Sgd::new(0.5));
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+        nn.set_quiet(true);
+        let exported = nn.export_to_string().unwrap();
+
+        let mut histories = Vec::<TrainingHistory>::new();
+        for _ in 0..2 {
+            let mut nn = NeuralNetwork::import(&exported);
+            nn.set_quiet(true);
+            let mut parameter = learning_parameter(batch_size, MinibatchSampling::WithReplacement);
+            parameter.iterations_num = 8;
+            parameter.seed = Some(0);
+            parameter.scheduler = Some(Box::new(ReduceOnPlateau::new(0.5, 0, 0.0, 0.01)));
+            histories.push(nn.learn(
+                parameter,
+                LearningResource {
+                    trn_data: data.clone(),
+                    trn_lbl_onehot: lbl_onehot.clone(),
+                    tst_data: data.clone(),
+                    tst_lbl_onehot: lbl_onehot.clone(),
+                    val_data: data.clone(),
+                    val_lbl_onehot: lbl_onehot.clone(),
+                }
+            ));
+        }
+
+        // Same seed, same losses and learning rates
+        let records = |history: &TrainingHistory| history.iterations.iter().map(|r| (r.test_loss, r.learning_rates.clone())).collect::<Vec<(f64, Vec<f64>)>>();
+        assert_eq!(records(&histories[0]), records(&histories[1]));
+    }
+
+    fn learning_parameter(batch_size: usize, sampling: MinibatchSampling) -> LearningParameter {
+        LearningParameter {
+            batch_size: batch_size,
+            iterations_num: 5,
+            sampling: sampling,
+            gradient_clipping: None,
+            scheduler: None,
+            seed: None,
+            checkpoint: None,
+            validation: None,
+            callbacks: Vec::new(),
+            threads_num: 1,
+        }
+    }
+
+    #[test]
+    fn test_minibatch_indexes_with_replacement() {
+        let parameter = learning_parameter(10, MinibatchSampling::WithReplacement);
+        assert_eq!(parameter.total_iterations_num(100), 5);
+        let mut sampler = parameter.minibatch_sampler(&Array2::<f64>::zeros((100, 1)));
+
+        // Reproducible by seed and iteration
+        let indexes = sampler.indexes(7, 3);
+        assert_eq!(indexes.len(), 10);
+        assert_eq!(indexes, sampler.indexes(7, 3));
+        assert_ne!(indexes, sampler.indexes(7, 4));
+    }
+
+    #[test]
+    fn test_minibatch_indexes_epoch() {
+        let lbl_onehot = Array2::<f64>::zeros((10, 1));
+
+        // Keep last
+        let parameter = learning_parameter(4, MinibatchSampling::Epoch { epochs_num: 2, last_batch: LastBatch::Keep });
+        assert_eq!(parameter.iterations_per_epoch(10), 3);
+        assert_eq!(parameter.total_iterations_num(10), 6);
+        let mut sampler = parameter.minibatch_sampler(&lbl_onehot);
+
+        for epoch in 0..2 {
+            let batches: Vec<Vec<usize>> = (0..3).map(|i| sampler.indexes(7, epoch * 3 + i)).collect();
+            assert_eq!(batches.iter().map(|b| b.len()).collect::<Vec<usize>>(), vec![4, 4, 2]);
+            // Every row once
+            let mut indexes: Vec<usize> = batches.into_iter().flatten().collect();
+            indexes.sort();
+            assert_eq!(indexes, (0..10).collect::<Vec<usize>>());
+        }
+        // Shuffled every epoch
+        let first_epoch: Vec<Vec<usize>> = (0..3).map(|i| sampler.indexes(7, i)).collect();
+        let second_epoch: Vec<Vec<usize>> = (3..6).map(|i| sampler.indexes(7, i)).collect();
+        assert_ne!(first_epoch, second_epoch);
+
+        // Resumed in the middle of an epoch gives the same batches
+        let mut resumed_sampler = parameter.minibatch_sampler(&lbl_onehot);
+        assert_eq!(resumed_sampler.indexes(7, 4), second_epoch[1]);
+        assert_eq!(resumed_sampler.indexes(7, 1), first_epoch[1]);
+        assert_eq!(resumed_sampler.indexes(7, 5), second_epoch[2]);
+
+        // Drop last
+        let parameter = learning_parameter(4, MinibatchSampling::Epoch { epochs_num: 2, last_batch: LastBatch::Drop });
+        assert_eq!(parameter.iterations_per_epoch(10), 2);
+        assert_eq!(parameter.total_iterations_num(10), 4);
+        let mut sampler = parameter.minibatch_sampler(&lbl_onehot);
+        let batches: Vec<Vec<usize>> = (0..2).map(|i| sampler.indexes(7, i)).collect();
+        assert_eq!(batches.iter().map(|b| b.len()).collect::<Vec<usize>>(), vec![4, 4]);
+        let mut indexes: Vec<usize> = batches.into_iter().flatten().collect();
+        indexes.sort();
+        indexes.dedup();
+        assert_eq!(indexes.len(), 8);
+    }
+
+    #[test]
+    fn test_minibatch_indexes_class_balanced() {
+        // 8 rows of class 0, 2 rows of class 1 and no row of class 2
+        let lbl_onehot = Array2::<f64>::from_shape_fn((10, 3), |(i, j)| if (i < 8 && j == 0) || (i >= 8 && j == 1) {1.0} else {0.0});
+        assert_eq!(class_indexes(&lbl_onehot), vec![(0..8).collect::<Vec<usize>>(), vec![8, 9]]);
+
+        let parameter = learning_parameter(6, MinibatchSampling::ClassBalanced);
+        assert_eq!(parameter.total_iterations_num(10), 5);
+        let mut sampler = parameter.minibatch_sampler(&lbl_onehot);
+
+        let indexes = sampler.indexes(7, 3);
+        assert_eq!(indexes.len(), 6);
+        assert_eq!(indexes.iter().filter(|i| **i >= 8).count(), 3);
+        assert_eq!(indexes, sampler.indexes(7, 3));
+
+        // Odd batch size
+        let mut sampler = learning_parameter(5, MinibatchSampling::ClassBalanced).minibatch_sampler(&lbl_onehot);
+        for iteration in 0..5 {
+            let minor_num = sampler.indexes(7, iteration).iter().filter(|i| **i >= 8).count();
+            assert!(minor_num == 2 || minor_num == 3);
+        }
+    }
+
+    #[test]
+    fn test_minibatch_indexes_weighted() {
+        // 9 rows of class 0 and 1 row of class 1
+        let lbl_onehot = Array2::<f64>::from_shape_fn((10, 2), |(i, j)| if (i < 9) == (j == 0) {1.0} else {0.0});
+        let weights = inverse_class_frequency_weights(&lbl_onehot);
+        assert_eq!(round_digit(weights[0], -6), round_digit(1.0 / 9.0, -6));
+        assert_eq!(weights[9], 1.0);
+
+        let mut sampler = learning_parameter(1000, MinibatchSampling::InverseClassFrequency).minibatch_sampler(&lbl_onehot);
+        let indexes = sampler.indexes(7, 0);
+        assert_eq!(indexes.len(), 1000);
+        assert_eq!(indexes, sampler.indexes(7, 0));
+        // Both classes about half
+        let minor_num = indexes.iter().filter(|i| **i == 9).count();
+        assert!(400 < minor_num && minor_num < 600, "{}", minor_num);
+
+        // Zero weight is never chosen
+        let first_excluded = |lbl_onehot: &Array2<f64>| (0..lbl_onehot.shape()[0]).map(|i| if i == 0 {0.0} else {1.0}).collect();
+        let mut sampler = learning_parameter(100, MinibatchSampling::Weighted(first_excluded)).minibatch_sampler(&lbl_onehot);
+        assert!(!sampler.indexes(7, 0).contains(&0));
+    }
+
+    #[test]
+    fn test_compute_gradient_partial_batch() {
+        let batch_size = 3;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        // Smaller batch than the network was built with
+        nn.zero_grad();
+        nn.compute_gradient(
+            &arr2(&
+                [
+                    [1.0, 2.0, 3.0],
+                    [4.0, 5.0, 6.0],
+                ]
+            ),
+            &arr2(&
+                [
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ]
+            )
+        );
+        assert_eq!(nn.forward(false).shape(), [2, 1]);
+        nn.visit_parameters(|_, parameter| {
+            assert_eq!(parameter.get_gradient().unwrap().shape(), parameter.get_value().shape());
+        });
+    }
+
+    #[test]
+    fn test_export_import_checkpoint() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Adam::new(0.01, 0.9, 0.999),
+            Momentum::new(0.1, 0.9)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let input = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let lbl = arr2(&
+            [
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]
+        );
+        nn.zero_grad();
+        nn.compute_gradient(&input, &lbl);
+        nn.step();
+
+        let checkpoint = Checkpoint {
+            iteration: 1,
+            seed: 42,
+            base_learning_rates: nn.learning_rates(),
+            losses: vec![1.4, 1.2],
+            correct_rates: vec![0.0, 0.5],
+            val_losses: vec![1.3],
+            val_correct_rates: vec![0.5],
+        };
+        let file_path = std::env::temp_dir().join("test_export_import_checkpoint.csv");
+        let file_path = file_path.to_str().unwrap();
+        nn.export_checkpoint(file_path, &checkpoint).unwrap();
+
+        let (mut imported, imported_checkpoint) = NeuralNetwork::import_checkpoint(file_path).unwrap();
+        assert_eq!(imported_checkpoint, checkpoint);
+        assert!(!std::path::Path::new(&format!("{}.tmp", file_path)).exists());
+
+        // Not a checkpoint
+        let network_file_path = std::env::temp_dir().join("test_export_import_checkpoint_network.csv");
+        fs::write(&network_file_path, nn.export_to_string().unwrap()).unwrap();
+        assert!(NeuralNetwork::import_checkpoint(network_file_path.to_str().unwrap()).is_err());
+
+        // Truncated checkpoint and malformed values
+        let data = fs::read_to_string(file_path).unwrap();
+        let truncated_file_path = std::env::temp_dir().join("test_export_import_checkpoint_truncated.csv");
+        for truncated in [&data[..data.len() / 2], "checkpoint\n1\n42\n", "checkpoint\nx\n42\n"].iter() {
+            fs::write(&truncated_file_path, truncated).unwrap();
+            assert!(NeuralNetwork::import_checkpoint(truncated_file_path.to_str().unwrap()).is_err());
+        }
+
+        // Next iteration gives the same parameters
+        for network in [&mut nn, &mut imported].iter_mut() {
+            network.zero_grad();
+            network.compute_gradient(&input, &lbl);
+            network.step();
+        }
+        let mut values = Vec::<Array2<f64>>::new();
+        nn.visit_parameters(|_, parameter| {
+            values.push(parameter.get_value().clone());
+        });
+        let mut i = 0;
+        imported.visit_parameters(|_, parameter| {
+            assert_eq!(parameter.get_value(), &values[i]);
+            i += 1;
+        });
+    }
+
+    #[test]
+    fn test_parameter_values() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let values = nn.parameter_values();
+        assert_eq!(values.len(), 2);
+
+        nn.zero_grad();
+        nn.compute_gradient(
+            &arr2(&
+                [
+                    [1.0, 2.0, 3.0],
+                    [4.0, 5.0, 6.0],
+                ]
+            ),
+            &arr2(&
+                [
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ]
+            )
+        );
+        nn.step();
+        assert_ne!(nn.parameter_values(), values);
+
+        nn.set_parameter_values(&values);
+        assert_eq!(nn.parameter_values(), values);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 2)));
+        let layers = Affine::new(
+            layers,
+            AffineDirectValue::new(arr2(&[[1.0, 0.0], [0.0, 1.0]]), Sgd::new(0.1)),
+            AffineDirectValue::new(arr2(&[[0.0, 0.0]]), Sgd::new(0.1))
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 2)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        // Guess the larger column. Last batch has 1 sample
+        let data = arr2(&
+            [
+                [2.0, 1.0],
+                [1.0, 2.0],
+                [3.0, 1.0],
+                [1.0, 3.0],
+                [1.0, 1.5],
+            ]
+        );
+        let lbl_onehot = arr2(&
+            [
+                [1.0, 0.0],
+                [0.0, 1.0],
+                [0.0, 1.0],
+                [0.0, 1.0],
+                [1.0, 0.0],
+            ]
+        );
+
+        let result = nn.evaluate(batch_size, &data, &lbl_onehot);
+
+        assert_eq!(result.sample_num, 5);
+        assert_eq!(result.correct_rate, 0.6);
+        assert_eq!(result.metrics.confusion_matrix, arr2(&
+            [
+                [1, 1],
+                [1, 2],
+            ]
+        ));
+        // Cross entropy of softmax for each sample
+        let expect_loss = [(2.0, 1.0, 0), (1.0, 2.0, 1), (3.0, 1.0, 1), (1.0, 3.0, 1), (1.0, 1.5, 0)].iter()
+            .map(|(a, b, t): &(f64, f64, usize)| {
+                let y = [a.exp() / (a.exp() + b.exp()), b.exp() / (a.exp() + b.exp())];
+                -(y[*t] + 1e-8).ln()
+            })
+            .sum::<f64>() / 5.0;
+        assert_eq!(round_digit(result.loss, -6), round_digit(expect_loss, -6));
+
+        // Same result every time
+        let result2 = nn.evaluate(batch_size, &data, &lbl_onehot);
+        assert_eq!(result2.loss, result.loss);
+    }
+
+    #[test]
+    fn test_clip_gradient() {
+        let batch_size = 2;
+
+        let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 3)));
+        let layers = Affine::new_random(
+            layers,
+            3,
+            4,
+            Sgd::new(0.1),
+            Sgd::new(0.1)
+        );
+        let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 4)));
+        let mut nn = NeuralNetwork::new(layers);
+
+        let input = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let lbl = arr2(&
+            [
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]
+        );
+
+        // Global norm
+        nn.zero_grad();
+        nn.compute_gradient(&input, &lbl);
+        let norm = nn.gradient_norm();
+        assert_eq!(nn.clip_gradient(&GradientClipping::GlobalNorm(norm * 2.0)), norm);
+        assert_eq!(round_digit(nn.gradient_norm(), -8), round_digit(norm, -8));
+        assert_eq!(nn.clip_gradient(&GradientClipping::GlobalNorm(norm / 2.0)), norm);
+        assert_eq!(round_digit(nn.gradient_norm(), -8), round_digit(norm / 2.0, -8));
+
+        // Value
+        nn.zero_grad();
+        nn.compute_gradient(&input, &lbl);
+        assert_eq!(nn.clip_gradient(&GradientClipping::Value(0.01)), norm);
+        nn.visit_parameters(|_, parameter| {
+            for g in parameter.get_gradient().unwrap() {
+                assert!(g.abs() <= 0.01);
+            }
+        });
+    }
+}
@@ -14,4 +14,19 @@ mod adagrad;
 pub use adagrad::*;
 
 mod adam;
-pub use adam::*;
\ No newline at end of file
+pub use adam::*;
+
+mod adamw;
+pub use adamw::*;
+
+mod nesterov;
+pub use nesterov::*;
+
+mod adadelta;
+pub use adadelta::*;
+
+mod nadam;
+pub use nadam::*;
+
+mod radam;
+pub use radam::*;
\ No newline at end of file
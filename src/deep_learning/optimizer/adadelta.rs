@@ -0,0 +1,136 @@
+// Reference
+// https://arxiv.org/abs/1212.5701
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::optimizer::*;
+use crate::deep_learning::common::*;
+
+pub struct AdaDelta {
+    learning_rate: f64,
+    grad_squared_avg: Option<Array2<f64>>,
+    delta_squared_avg: Option<Array2<f64>>,
+    friction: f64,
+}
+impl AdaDelta {
+    // learning_rate is 1.0 in the original paper
+    pub fn new(learning_rate: f64, friction: f64) -> Self {
+        Self {
+            learning_rate: learning_rate,
+            grad_squared_avg: None,
+            delta_squared_avg: None,
+            friction: friction,
+        }
+    }
+    pub fn optimizer_label() -> &'static str {
+        "adadelta"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // state
+        let grad_squared_avg = import_optimizer_state(lines)?;
+        let delta_squared_avg = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                grad_squared_avg: grad_squared_avg,
+                delta_squared_avg: delta_squared_avg,
+                friction: friction,
+            }
+        )
+    }
+}
+impl Optimizer for AdaDelta {
+    fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
+        if self.grad_squared_avg.is_none() {
+            self.grad_squared_avg = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let grad_squared_avg = self.grad_squared_avg.as_mut().unwrap();
+
+        if self.delta_squared_avg.is_none() {
+            self.delta_squared_avg = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let delta_squared_avg = self.delta_squared_avg.as_mut().unwrap();
+
+        grad_squared_avg.assign(&(grad_squared_avg.clone() * self.friction + gradient * gradient * (1.0 - self.friction)));
+
+        let delta =
+            sqrt_arr2(&(delta_squared_avg.clone() + (10.0 as f64).powi(-6))) /
+            sqrt_arr2(&(grad_squared_avg.clone() + (10.0 as f64).powi(-6))) *
+            gradient;
+
+        delta_squared_avg.assign(&(delta_squared_avg.clone() * self.friction + &delta * &delta * (1.0 - self.friction)));
+
+        return target - delta * self.learning_rate;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},", self.learning_rate, self.friction)?;
+        export_optimizer_state(file, &self.grad_squared_avg)?;
+        export_optimizer_state(file, &self.delta_squared_avg)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    #[test]
+    fn update() {
+        let mut adadelta = AdaDelta::new(1.0, 0.9);
+        let target = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let gradient = arr2(&
+            [
+                [1.0, 3.0, 5.0],
+                [2.0, 4.0, 6.0],
+            ]
+        );
+
+        let updated = adadelta.update(&target, &gradient);
+
+        let grad_squared_avg = gradient.clone() * gradient.clone() * 0.1;
+        let delta =
+            (10.0 as f64).powi(-6).sqrt() /
+            sqrt_arr2(&(grad_squared_avg.clone() + (10.0 as f64).powi(-6))) *
+            gradient.clone();
+        let delta_squared_avg = delta.clone() * delta.clone() * 0.1;
+        let expect_updated = target.clone() - delta.clone();
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+
+        let updated = adadelta.update(&target, &gradient);
+
+        let grad_squared_avg = grad_squared_avg * 0.9 + gradient.clone() * gradient.clone() * 0.1;
+        let delta =
+            sqrt_arr2(&(delta_squared_avg.clone() + (10.0 as f64).powi(-6))) /
+            sqrt_arr2(&(grad_squared_avg.clone() + (10.0 as f64).powi(-6))) *
+            gradient.clone();
+        let expect_updated = target.clone() - delta.clone();
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+    }
+}
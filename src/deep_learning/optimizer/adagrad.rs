@@ -1,3 +1,4 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
@@ -16,6 +17,26 @@ impl AdaGrad {
             grad_squared_sum: None,
         }
     }
+    pub fn optimizer_label() -> &'static str {
+        "adagrad"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // state
+        let grad_squared_sum = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                grad_squared_sum: grad_squared_sum,
+            }
+        )
+    }
 }
 impl Optimizer for AdaGrad {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
@@ -32,6 +53,19 @@ impl Optimizer for AdaGrad {
                 sqrt_arr2(&(grad_squared_sum.clone() + (10.0 as f64).powi(-6))) *
                 gradient;
     }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},", self.learning_rate)?;
+        export_optimizer_state(file, &self.grad_squared_sum)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1,3 +1,4 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
@@ -11,19 +12,55 @@ pub struct Adam {
     v: Option<Array2<f64>>,
     friction_m: f64,
     friction_v: f64,
+    epsilon: f64,
     update_count: u32,
 }
 impl Adam {
     pub fn new(learning_rate: f64, friction_m: f64, friction_v: f64) -> Self {
+        Self::new_with_epsilon(learning_rate, friction_m, friction_v, (10.0 as f64).powi(-6))
+    }
+    // Epsilon is added inside the sqrt
+    pub fn new_with_epsilon(learning_rate: f64, friction_m: f64, friction_v: f64, epsilon: f64) -> Self {
         Self {
             learning_rate: learning_rate,
             m: None,
             v: None,
             friction_m: friction_m,
             friction_v: friction_v,
+            epsilon: epsilon,
             update_count: 0,
         }
     }
+    pub fn optimizer_label() -> &'static str {
+        "adam"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_m = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_v = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let epsilon = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let update_count = next_import_value(&mut line_split)?.parse::<u32>()?;
+        // state
+        let m = import_optimizer_state(lines)?;
+        let v = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                m: m,
+                v: v,
+                friction_m: friction_m,
+                friction_v: friction_v,
+                epsilon: epsilon,
+                update_count: update_count,
+            }
+        )
+    }
 }
 impl Optimizer for Adam {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
@@ -51,9 +88,23 @@ impl Optimizer for Adam {
             target - 
                 self.learning_rate *
                 m_d / 
-                sqrt_arr2(&(v_d + (10.0 as f64).powi(-6)))
+                sqrt_arr2(&(v_d + self.epsilon))
         ;
     }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},{},{},{},", self.learning_rate, self.friction_m, self.friction_v, self.epsilon, self.update_count)?;
+        export_optimizer_state(file, &self.m)?;
+        export_optimizer_state(file, &self.v)?;
+        Ok(())
+    }
 }
 
 
@@ -0,0 +1,241 @@
+// Reference
+// https://arxiv.org/abs/1711.05101 (AdamW)
+// https://openreview.net/forum?id=ryQu7f-RZ (AMSGrad)
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::optimizer::*;
+use crate::deep_learning::common::*;
+
+// Adam with weight decay applied directly to the parameter
+pub struct AdamW {
+    learning_rate: f64,
+    m: Option<Array2<f64>>,
+    v: Option<Array2<f64>>,
+    v_max: Option<Array2<f64>>,
+    friction_m: f64,
+    friction_v: f64,
+    weight_decay: f64,
+    epsilon: f64,
+    amsgrad: bool,
+    update_count: u32,
+}
+impl AdamW {
+    pub fn new(learning_rate: f64, friction_m: f64, friction_v: f64, weight_decay: f64) -> Self {
+        Self::new_with_options(learning_rate, friction_m, friction_v, weight_decay, (10.0 as f64).powi(-8), false)
+    }
+    pub fn new_with_options(learning_rate: f64, friction_m: f64, friction_v: f64, weight_decay: f64, epsilon: f64, amsgrad: bool) -> Self {
+        Self {
+            learning_rate: learning_rate,
+            m: None,
+            v: None,
+            v_max: None,
+            friction_m: friction_m,
+            friction_v: friction_v,
+            weight_decay: weight_decay,
+            epsilon: epsilon,
+            amsgrad: amsgrad,
+            update_count: 0,
+        }
+    }
+    pub fn optimizer_label() -> &'static str {
+        "adamw"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_m = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_v = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let weight_decay = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let epsilon = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let amsgrad = next_import_value(&mut line_split)?.parse::<bool>()?;
+        let update_count = next_import_value(&mut line_split)?.parse::<u32>()?;
+        // state
+        let m = import_optimizer_state(lines)?;
+        let v = import_optimizer_state(lines)?;
+        let v_max = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                m: m,
+                v: v,
+                v_max: v_max,
+                friction_m: friction_m,
+                friction_v: friction_v,
+                weight_decay: weight_decay,
+                epsilon: epsilon,
+                amsgrad: amsgrad,
+                update_count: update_count,
+            }
+        )
+    }
+}
+impl Optimizer for AdamW {
+    fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
+        self.update_count += 1;
+
+        if self.m.is_none() {
+            self.m = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let m = self.m.as_mut().unwrap();
+
+        if self.v.is_none() {
+            self.v = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let v = self.v.as_mut().unwrap();
+
+        m.assign(&(self.friction_m * m.clone() + (1.0 - self.friction_m) * gradient));
+
+        v.assign(&(self.friction_v * v.clone() + (1.0 - self.friction_v) * gradient * gradient));
+
+        // AMSGrad uses the maximum of v so far
+        let v = if self.amsgrad {
+            if self.v_max.is_none() {
+                self.v_max = Some(Array2::<f64>::zeros(target.dim()));
+            }
+            let v_max = self.v_max.as_mut().unwrap();
+            v_max.zip_mut_with(v, |max, v| if *max < *v {*max = *v});
+            v_max.clone()
+        } else {
+            v.clone()
+        };
+
+        let m_d = m.clone() / (1.0 - self.friction_m.powi(self.update_count as i32));
+
+        let v_d = v / (1.0 - self.friction_v.powi(self.update_count as i32));
+
+        // Epsilon is added outside the sqrt as in the paper
+        return
+            target -
+                self.learning_rate * (
+                    m_d /
+                    (sqrt_arr2(&v_d) + self.epsilon) +
+                    self.weight_decay * target
+                )
+        ;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},{},{},{},{},{},", self.learning_rate, self.friction_m, self.friction_v, self.weight_decay, self.epsilon, self.amsgrad, self.update_count)?;
+        export_optimizer_state(file, &self.m)?;
+        export_optimizer_state(file, &self.v)?;
+        export_optimizer_state(file, &self.v_max)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    #[test]
+    fn update() {
+        let mut adamw = AdamW::new(0.1, 0.9, 0.99, 0.01);
+        let target = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let gradient = arr2(&
+            [
+                [1.0, 3.0, 5.0],
+                [2.0, 4.0, 6.0],
+            ]
+        );
+
+        let updated = adamw.update(&target, &gradient);
+
+        let m = 0.1 * gradient.clone();
+        let v = 0.01 * gradient.clone() * gradient.clone();
+        let m_d = m.clone() / (1.0 - (0.9 as f64).powf(1.0));
+        let v_d = v.clone() / (1.0 - (0.99 as f64).powf(1.0));
+
+        let expect_updated =
+            target.clone() -
+                0.1 * (
+                    m_d /
+                    (v_d.mapv(|v: f64| -> f64 {v.sqrt()}) + (10.0 as f64).powi(-8)) +
+                    0.01 * target.clone()
+                );
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+
+
+        let updated = adamw.update(&target, &gradient);
+
+        let m = m * 0.9 + 0.1 * gradient.clone();
+        let v = v * 0.99 + 0.01 * gradient.clone() * gradient.clone();
+        let m_d = m.clone() / (1.0 - (0.9 as f64).powf(2.0));
+        let v_d = v.clone() / (1.0 - (0.99 as f64).powf(2.0));
+
+        let expect_updated =
+            target.clone() -
+                0.1 * (
+                    m_d /
+                    (v_d.mapv(|v: f64| -> f64 {v.sqrt()}) + (10.0 as f64).powi(-8)) +
+                    0.01 * target.clone()
+                );
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+    }
+
+    #[test]
+    fn update_amsgrad() {
+        let mut adamw = AdamW::new_with_options(0.1, 0.9, 0.99, 0.0, 0.001, true);
+        let target = arr2(&
+            [
+                [1.0, 2.0],
+            ]
+        );
+        let gradient1 = arr2(&
+            [
+                [4.0, 4.0],
+            ]
+        );
+        let gradient2 = arr2(&
+            [
+                [0.0, 8.0],
+            ]
+        );
+
+        adamw.update(&target, &gradient1);
+        let updated = adamw.update(&target, &gradient2);
+
+        let m = (0.1 * gradient1.clone()) * 0.9 + 0.1 * gradient2.clone();
+        let v1 = 0.01 * gradient1.clone() * gradient1.clone();
+        let v2 = v1.clone() * 0.99 + 0.01 * gradient2.clone() * gradient2.clone();
+        // Keep the larger v
+        let v_max = arr2(&
+            [
+                [v1[(0, 0)].max(v2[(0, 0)]), v1[(0, 1)].max(v2[(0, 1)])],
+            ]
+        );
+        let m_d = m.clone() / (1.0 - (0.9 as f64).powf(2.0));
+        let v_d = v_max.clone() / (1.0 - (0.99 as f64).powf(2.0));
+
+        let expect_updated =
+            target.clone() -
+                0.1 *
+                m_d /
+                (v_d.mapv(|v: f64| -> f64 {v.sqrt()}) + 0.001);
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+    }
+}
@@ -1,7 +1,9 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
 
+use crate::deep_learning::common::*;
 use crate::deep_learning::optimizer::*;
 
 pub struct Momentum {
@@ -17,6 +19,28 @@ impl Momentum {
             friction: friction,
         }
     }
+    pub fn optimizer_label() -> &'static str {
+        "momentum"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // state
+        let velocity = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                velocity: velocity,
+                friction: friction,
+            }
+        )
+    }
 }
 impl Optimizer for Momentum {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
@@ -29,6 +53,19 @@ impl Optimizer for Momentum {
 
         return target + velocity.clone();
     }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},", self.learning_rate, self.friction)?;
+        export_optimizer_state(file, &self.velocity)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
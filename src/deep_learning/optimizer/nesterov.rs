@@ -0,0 +1,112 @@
+// Reference
+// https://arxiv.org/abs/1212.0901
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::common::*;
+use crate::deep_learning::optimizer::*;
+
+// Nesterov accelerated momentum
+pub struct Nesterov {
+    learning_rate: f64,
+    velocity: Option<Array2<f64>>,
+    friction: f64,
+}
+impl Nesterov {
+    pub fn new(learning_rate: f64, friction: f64) -> Self {
+        Self {
+            learning_rate: learning_rate,
+            velocity: None,
+            friction: friction,
+        }
+    }
+    pub fn optimizer_label() -> &'static str {
+        "nesterov"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // state
+        let velocity = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                velocity: velocity,
+                friction: friction,
+            }
+        )
+    }
+}
+impl Optimizer for Nesterov {
+    fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
+        if self.velocity.is_none() {
+            self.velocity = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let velocity = self.velocity.as_mut().unwrap();
+
+        velocity.assign(&(velocity.clone() * self.friction - gradient * self.learning_rate));
+
+        // Look ahead along the updated velocity
+        return target + velocity.clone() * self.friction - gradient * self.learning_rate;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},", self.learning_rate, self.friction)?;
+        export_optimizer_state(file, &self.velocity)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+
+    #[test]
+    fn update() {
+        let mut nesterov = Nesterov::new(0.1, 0.9);
+        let target = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let gradient = arr2(&
+            [
+                [1.0, 3.0, 5.0],
+                [2.0, 4.0, 6.0],
+            ]
+        );
+
+        let updated = nesterov.update(&target, &gradient);
+
+        let velocity = gradient.clone() * -0.1;
+        let expect_updated = target.clone() + velocity.clone() * 0.9 - gradient.clone() * 0.1;
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+
+        let updated = nesterov.update(&target, &gradient);
+
+        let velocity = velocity * 0.9 - gradient.clone() * 0.1;
+        let expect_updated = target.clone() + velocity.clone() * 0.9 - gradient.clone() * 0.1;
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+    }
+}
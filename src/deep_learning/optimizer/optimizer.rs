@@ -1,11 +1,158 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
+    Axis,
 };
 
+use crate::deep_learning::common::*;
+use crate::deep_learning::optimizer::*;
+
 #[cfg_attr(test, mockall::automock)]
 pub trait Optimizer {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64>;
+    fn learning_rate(&self) -> f64;
+    // Changed by a learning rate scheduler while learning
+    fn set_learning_rate(&mut self, learning_rate: f64);
+    // Write label, hyperparameters and state to continue updating after import
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>>;
 }
 
 // Reference
 // https://data-science.gr.jp/theory/tml_optimizer_of_gradient_descent.html
+
+pub fn import_optimizer<'a, T>(lines: &mut T) -> Result<Box<dyn Optimizer>, Box<dyn std::error::Error>>
+    where T: Iterator<Item = &'a str>
+{
+    let optimizer_label = next_import_value(lines)?;
+
+    let optimizer: Box<dyn Optimizer> =
+    if optimizer_label == Sgd::optimizer_label() {
+        Box::new(Sgd::import(lines)?)
+    } else if optimizer_label == Momentum::optimizer_label() {
+        Box::new(Momentum::import(lines)?)
+    } else if optimizer_label == Rmsprop::optimizer_label() {
+        Box::new(Rmsprop::import(lines)?)
+    } else if optimizer_label == AdaGrad::optimizer_label() {
+        Box::new(AdaGrad::import(lines)?)
+    } else if optimizer_label == Adam::optimizer_label() {
+        Box::new(Adam::import(lines)?)
+    } else if optimizer_label == AdamW::optimizer_label() {
+        Box::new(AdamW::import(lines)?)
+    } else if optimizer_label == Nesterov::optimizer_label() {
+        Box::new(Nesterov::import(lines)?)
+    } else if optimizer_label == AdaDelta::optimizer_label() {
+        Box::new(AdaDelta::import(lines)?)
+    } else if optimizer_label == NAdam::optimizer_label() {
+        Box::new(NAdam::import(lines)?)
+    } else if optimizer_label == RAdam::optimizer_label() {
+        Box::new(RAdam::import(lines)?)
+    } else {
+        return Err(format!("No match optimizer label '{}'", optimizer_label).into());
+    };
+
+    return Ok(optimizer);
+}
+
+// State array is written as "none" before the first update, otherwise shape line and rows
+#[cfg (not (target_family = "wasm"))]
+pub fn export_optimizer_state(file: &mut dyn Write, state: &Option<Array2<f64>>) -> Result<(), Box<dyn std::error::Error>> {
+    match state {
+        None => writeln!(file, "none")?,
+        Some(state) => {
+            writeln!(file, "{},{}", state.shape()[0], state.shape()[1])?;
+            for row in state.axis_iter(Axis(0)) {
+                for v in row {
+                    write!(file, "{},", v)?;
+                }
+                writeln!(file, "")?;
+            }
+        },
+    }
+    Ok(())
+}
+
+pub fn import_optimizer_state<'a, T>(lines: &mut T) -> Result<Option<Array2<f64>>, Box<dyn std::error::Error>>
+    where T: Iterator<Item = &'a str>
+{
+    let shape_line = next_import_value(lines)?;
+    if shape_line == "none" {
+        return Ok(None);
+    }
+    let mut shape_line_split = shape_line.split(',');
+    let dim: (usize, usize) = (next_import_value(&mut shape_line_split)?.parse::<usize>()?, next_import_value(&mut shape_line_split)?.parse::<usize>()?);
+    let mut state = Array2::<f64>::zeros(dim);
+    for row_i in 0..dim.0 {
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        for col_i in 0..dim.1 {
+            state[(row_i, col_i)] = next_import_value(&mut line_split)?.parse::<f64>()?;
+        }
+    }
+    return Ok(Some(state));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    fn export_and_import(optimizer: &dyn Optimizer) -> Box<dyn Optimizer> {
+        let mut buffer = Vec::<u8>::new();
+        optimizer.export(&mut buffer).unwrap();
+
+        let data = String::from_utf8(buffer).unwrap();
+        let mut lines = data.lines();
+        let imported = import_optimizer(&mut lines).unwrap();
+        assert_eq!(lines.next(), None);
+        return imported;
+    }
+
+    // Imported optimizer continues updating exactly like the original one
+    fn assert_resume<TO>(mut optimizer: TO)
+        where TO: Optimizer
+    {
+        let target = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let gradient = arr2(&
+            [
+                [1.0, 3.0, 5.0],
+                [2.0, -4.0, 0.5],
+            ]
+        );
+
+        // Before the first update
+        let mut imported = export_and_import(&optimizer);
+        assert_eq!(imported.learning_rate(), optimizer.learning_rate());
+        let updated = optimizer.update(&target, &gradient);
+        assert_eq!(imported.update(&target, &gradient), updated);
+
+        // After updates
+        let target = optimizer.update(&updated, &gradient);
+        let mut imported = export_and_import(&optimizer);
+        for _ in 0..3 {
+            assert_eq!(imported.update(&target, &gradient), optimizer.update(&target, &gradient));
+        }
+    }
+
+    #[test]
+    fn test_export_import() {
+        assert_resume(Sgd::new(0.1));
+        assert_resume(Momentum::new(0.1, 0.9));
+        assert_resume(Rmsprop::new(0.1, 0.9));
+        assert_resume(AdaGrad::new(0.1));
+        assert_resume(Adam::new(0.1, 0.9, 0.999));
+        assert_resume(AdamW::new_with_options(0.1, 0.9, 0.999, 0.01, 0.001, true));
+        assert_resume(Nesterov::new(0.1, 0.9));
+        assert_resume(AdaDelta::new(1.0, 0.9));
+        assert_resume(NAdam::new(0.1, 0.9, 0.999));
+        assert_resume(RAdam::new(0.1, 0.9, 0.9));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,178 @@
+// Reference
+// https://arxiv.org/abs/1908.03265
+use std::io::Write;
+use ndarray::prelude::{
+    Array2,
+};
+
+use crate::deep_learning::optimizer::*;
+use crate::deep_learning::common::*;
+
+// Rectified Adam
+pub struct RAdam {
+    learning_rate: f64,
+    m: Option<Array2<f64>>,
+    v: Option<Array2<f64>>,
+    friction_m: f64,
+    friction_v: f64,
+    update_count: u32,
+}
+impl RAdam {
+    pub fn new(learning_rate: f64, friction_m: f64, friction_v: f64) -> Self {
+        Self {
+            learning_rate: learning_rate,
+            m: None,
+            v: None,
+            friction_m: friction_m,
+            friction_v: friction_v,
+            update_count: 0,
+        }
+    }
+    pub fn optimizer_label() -> &'static str {
+        "radam"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_m = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction_v = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let update_count = next_import_value(&mut line_split)?.parse::<u32>()?;
+        // state
+        let m = import_optimizer_state(lines)?;
+        let v = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                m: m,
+                v: v,
+                friction_m: friction_m,
+                friction_v: friction_v,
+                update_count: update_count,
+            }
+        )
+    }
+}
+impl Optimizer for RAdam {
+    fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
+        self.update_count += 1;
+
+        if self.m.is_none() {
+            self.m = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let m = self.m.as_mut().unwrap();
+
+        if self.v.is_none() {
+            self.v = Some(Array2::<f64>::zeros(target.dim()));
+        }
+        let v = self.v.as_mut().unwrap();
+
+        m.assign(&(self.friction_m * m.clone() + (1.0 - self.friction_m) * gradient));
+
+        v.assign(&(self.friction_v * v.clone() + (1.0 - self.friction_v) * gradient * gradient));
+
+        let m_d = m.clone() / (1.0 - self.friction_m.powi(self.update_count as i32));
+
+        // Length of the approximated simple moving average
+        let friction_v_t = self.friction_v.powi(self.update_count as i32);
+        let rho_inf = 2.0 / (1.0 - self.friction_v) - 1.0;
+        let rho = rho_inf - 2.0 * self.update_count as f64 * friction_v_t / (1.0 - friction_v_t);
+
+        // Variance is not tractable in early steps. Update without adaptive learning rate
+        if rho <= 4.0 {
+            return target - self.learning_rate * m_d;
+        }
+
+        let v_d = v.clone() / (1.0 - friction_v_t);
+        let rectification = (
+            (rho - 4.0) * (rho - 2.0) * rho_inf /
+            ((rho_inf - 4.0) * (rho_inf - 2.0) * rho)
+        ).sqrt();
+
+        return
+            target -
+                self.learning_rate *
+                rectification *
+                m_d /
+                sqrt_arr2(&(v_d + (10.0 as f64).powi(-6)))
+        ;
+    }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},{},{},", self.learning_rate, self.friction_m, self.friction_v, self.update_count)?;
+        export_optimizer_state(file, &self.m)?;
+        export_optimizer_state(file, &self.v)?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ndarray::prelude::{
+        arr2,
+    };
+
+    #[test]
+    fn update() {
+        let mut radam = RAdam::new(0.1, 0.9, 0.9);
+        let target = arr2(&
+            [
+                [1.0, 2.0, 3.0],
+                [4.0, 5.0, 6.0],
+            ]
+        );
+        let gradient = arr2(&
+            [
+                [1.0, 3.0, 5.0],
+                [2.0, 4.0, 6.0],
+            ]
+        );
+
+        // rho_inf = 19, rho_1 = 1
+        let updated = radam.update(&target, &gradient);
+
+        let mut m = 0.1 * gradient.clone();
+        let mut v = 0.1 * gradient.clone() * gradient.clone();
+        let m_d = m.clone() / (1.0 - (0.9 as f64).powf(1.0));
+
+        let expect_updated = target.clone() - 0.1 * m_d;
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+
+        // rho_5 = 19 - 10 * 0.9^5 / (1 - 0.9^5) > 4
+        for _ in 2..5 {
+            m = m * 0.9 + 0.1 * gradient.clone();
+            v = v * 0.9 + 0.1 * gradient.clone() * gradient.clone();
+            radam.update(&target, &gradient);
+        }
+        let updated = radam.update(&target, &gradient);
+
+        let m = m * 0.9 + 0.1 * gradient.clone();
+        let v = v * 0.9 + 0.1 * gradient.clone() * gradient.clone();
+        let m_d = m.clone() / (1.0 - (0.9 as f64).powf(5.0));
+        let v_d = v.clone() / (1.0 - (0.9 as f64).powf(5.0));
+        let rho_inf = 19.0;
+        let rho = rho_inf - 2.0 * 5.0 * (0.9 as f64).powf(5.0) / (1.0 - (0.9 as f64).powf(5.0));
+        let rectification = ((rho - 4.0) * (rho - 2.0) * rho_inf / ((rho_inf - 4.0) * (rho_inf - 2.0) * rho)).sqrt();
+
+        let expect_updated =
+            target.clone() -
+                0.1 * rectification /
+                (v_d + (10.0 as f64).powi(-6)).mapv(|v: f64| -> f64 {v.sqrt()})
+                    * m_d;
+        assert_eq!(round_digit_arr2(&updated, -6), round_digit_arr2(&expect_updated, -6));
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
@@ -18,6 +19,28 @@ impl Rmsprop {
             friction: friction,
         }
     }
+    pub fn optimizer_label() -> &'static str {
+        "rmsprop"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+        let friction = next_import_value(&mut line_split)?.parse::<f64>()?;
+        // state
+        let velocity = import_optimizer_state(lines)?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+                velocity: velocity,
+                friction: friction,
+            }
+        )
+    }
 }
 impl Optimizer for Rmsprop {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
@@ -33,6 +56,19 @@ impl Optimizer for Rmsprop {
                 sqrt_arr2(&(velocity.clone() + (10.0 as f64).powi(-6)))
                 * gradient;
     }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},{},", self.learning_rate, self.friction)?;
+        export_optimizer_state(file, &self.velocity)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,9 @@
+use std::io::Write;
 use ndarray::prelude::{
     Array2,
 };
 
+use crate::deep_learning::common::*;
 use crate::deep_learning::optimizer::*;
 
 pub struct Sgd {
@@ -13,11 +15,40 @@ impl Sgd {
             learning_rate: learning_rate,
         }
     }
+    pub fn optimizer_label() -> &'static str {
+        "sgd"
+    }
+    pub fn import<'a, T>(lines: &mut T) -> Result<Self, Box<dyn std::error::Error>>
+        where T: Iterator<Item = &'a str>
+    {
+        // hyperparameters
+        let line = next_import_value(lines)?;
+        let mut line_split = line.split(',');
+        let learning_rate = next_import_value(&mut line_split)?.parse::<f64>()?;
+
+        Ok(
+            Self {
+                learning_rate: learning_rate,
+            }
+        )
+    }
 }
 impl Optimizer for Sgd {
     fn update(&mut self, target: &Array2<f64>, gradient: &Array2<f64>) -> Array2<f64> {
         return target - gradient * self.learning_rate;
     }
+    fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+    fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+    #[cfg (not (target_family = "wasm"))]
+    fn export(&self, file: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(file, "{}", Self::optimizer_label())?;
+        writeln!(file, "{},", self.learning_rate)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,20 @@
+mod scheduler;
+pub use scheduler::*;
+
+mod constant;
+pub use constant::*;
+
+mod step_decay;
+pub use step_decay::*;
+
+mod exponential_decay;
+pub use exponential_decay::*;
+
+mod cosine_annealing;
+pub use cosine_annealing::*;
+
+mod linear_warmup;
+pub use linear_warmup::*;
+
+mod reduce_on_plateau;
+pub use reduce_on_plateau::*;
\ No newline at end of file
@@ -0,0 +1,14 @@
+use crate::deep_learning::scheduler::*;
+
+// Keep the initial learning rate
+pub struct ConstantLearningRate {}
+impl ConstantLearningRate {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+impl LearningRateScheduler for ConstantLearningRate {
+    fn factor(&mut self, _iteration: u32) -> f64 {
+        1.0
+    }
+}
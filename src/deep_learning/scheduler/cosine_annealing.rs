@@ -0,0 +1,66 @@
+// Reference
+// https://arxiv.org/abs/1608.03983
+use std::f64::consts::PI;
+
+use crate::deep_learning::scheduler::*;
+
+// Cosine annealing with warm restarts (SGDR)
+// Factor decreases from 1 to min_factor along a half cosine in each period.
+// Length of the next period is multiplied by period_mult.
+pub struct CosineAnnealingWarmRestarts {
+    first_period: u32,
+    period_mult: u32,
+    min_factor: f64,
+}
+impl CosineAnnealingWarmRestarts {
+    pub fn new(first_period: u32, period_mult: u32, min_factor: f64) -> Self {
+        if first_period == 0 || period_mult == 0 {
+            panic!("first_period and period_mult must be greater than 0.");
+        }
+        Self {
+            first_period: first_period,
+            period_mult: period_mult,
+            min_factor: min_factor,
+        }
+    }
+}
+impl LearningRateScheduler for CosineAnnealingWarmRestarts {
+    fn factor(&mut self, iteration: u32) -> f64 {
+        // Find the period containing the iteration
+        let mut iteration_in_period = iteration;
+        let mut period = self.first_period;
+        while iteration_in_period >= period {
+            iteration_in_period -= period;
+            period *= self.period_mult;
+        }
+
+        let progress = iteration_in_period as f64 / period as f64;
+        return self.min_factor + (1.0 - self.min_factor) * (1.0 + (PI * progress).cos()) / 2.0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+
+    #[test]
+    fn test_factor() {
+        let mut scheduler = CosineAnnealingWarmRestarts::new(2, 2, 0.0);
+
+        let factors: Vec<f64> = (0..7).map(|i| round_digit(scheduler.factor(i), -6)).collect();
+
+        // Periods are [0, 1], [2, 5], [6, 13]
+        assert_eq!(factors, vec![1.0, 0.5, 1.0, 0.853553, 0.5, 0.146447, 1.0].iter().map(|f| round_digit(*f, -6)).collect::<Vec<f64>>());
+    }
+
+    #[test]
+    fn test_factor_min() {
+        let mut scheduler = CosineAnnealingWarmRestarts::new(2, 1, 0.2);
+
+        let factors: Vec<f64> = (0..4).map(|i| round_digit(scheduler.factor(i), -6)).collect();
+
+        assert_eq!(factors, vec![1.0, 0.6, 1.0, 0.6].iter().map(|f| round_digit(*f, -6)).collect::<Vec<f64>>());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::deep_learning::scheduler::*;
+
+// Multiply gamma every iteration
+pub struct ExponentialDecay {
+    gamma: f64,
+}
+impl ExponentialDecay {
+    pub fn new(gamma: f64) -> Self {
+        Self {
+            gamma: gamma,
+        }
+    }
+}
+impl LearningRateScheduler for ExponentialDecay {
+    fn factor(&mut self, iteration: u32) -> f64 {
+        return self.gamma.powi(iteration as i32);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+
+    #[test]
+    fn test_factor() {
+        let mut scheduler = ExponentialDecay::new(0.9);
+
+        let factors: Vec<f64> = (0..4).map(|i| round_digit(scheduler.factor(i), -6)).collect();
+
+        assert_eq!(factors, vec![1.0, 0.9, 0.81, 0.729].iter().map(|f| round_digit(*f, -6)).collect::<Vec<f64>>());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::deep_learning::scheduler::*;
+
+// Increase factor linearly from start_factor to 1 in the first warmup_iterations.
+// After warmup, the inner scheduler is used from its iteration 0.
+pub struct LinearWarmup {
+    warmup_iterations: u32,
+    start_factor: f64,
+    scheduler: Box<dyn LearningRateScheduler>,
+}
+impl LinearWarmup {
+    pub fn new<TS>(warmup_iterations: u32, start_factor: f64, scheduler: TS) -> Self
+        where TS: LearningRateScheduler + 'static
+    {
+        Self {
+            warmup_iterations: warmup_iterations,
+            start_factor: start_factor,
+            scheduler: Box::new(scheduler),
+        }
+    }
+}
+impl LearningRateScheduler for LinearWarmup {
+    fn factor(&mut self, iteration: u32) -> f64 {
+        if iteration < self.warmup_iterations {
+            let progress = iteration as f64 / self.warmup_iterations as f64;
+            return self.start_factor + (1.0 - self.start_factor) * progress;
+        }
+        return self.scheduler.factor(iteration - self.warmup_iterations);
+    }
+    fn observes_loss(&self) -> bool {
+        self.scheduler.observes_loss()
+    }
+    fn observe_loss(&mut self, loss: f64) {
+        self.scheduler.observe_loss(loss);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+
+    #[test]
+    fn test_factor() {
+        let mut scheduler = LinearWarmup::new(4, 0.2, StepDecay::new(2, 0.5));
+
+        let factors: Vec<f64> = (0..8).map(|i| round_digit(scheduler.factor(i), -6)).collect();
+
+        assert_eq!(factors, vec![0.2, 0.4, 0.6, 0.8, 1.0, 1.0, 0.5, 0.5].iter().map(|f| round_digit(*f, -6)).collect::<Vec<f64>>());
+    }
+
+    #[test]
+    fn test_observe_loss() {
+        let mut inner = MockLearningRateScheduler::new();
+        inner.expect_observe_loss()
+            .withf(|loss: &f64| *loss == 0.5)
+            .times(1)
+            .return_const(());
+        let mut scheduler = LinearWarmup::new(4, 0.2, inner);
+
+        scheduler.observe_loss(0.5);
+    }
+
+    #[test]
+    fn test_observes_loss() {
+        assert_eq!(LinearWarmup::new(4, 0.2, StepDecay::new(2, 0.5)).observes_loss(), false);
+        assert_eq!(LinearWarmup::new(4, 0.2, ReduceOnPlateau::new(0.5, 2, 0.0, 0.01)).observes_loss(), true);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::deep_learning::scheduler::*;
+
+// Multiply gamma when test loss has not improved by more than min_delta for patience iterations
+pub struct ReduceOnPlateau {
+    gamma: f64,
+    patience: u32,
+    min_delta: f64,
+    min_factor: f64,
+    current_factor: f64,
+    best_loss: Option<f64>,
+    wait: u32,
+}
+impl ReduceOnPlateau {
+    pub fn new(gamma: f64, patience: u32, min_delta: f64, min_factor: f64) -> Self {
+        Self {
+            gamma: gamma,
+            patience: patience,
+            min_delta: min_delta,
+            min_factor: min_factor,
+            current_factor: 1.0,
+            best_loss: None,
+            wait: 0,
+        }
+    }
+}
+impl LearningRateScheduler for ReduceOnPlateau {
+    fn factor(&mut self, _iteration: u32) -> f64 {
+        self.current_factor
+    }
+    fn observes_loss(&self) -> bool {
+        true
+    }
+    fn observe_loss(&mut self, loss: f64) {
+        let improved = match self.best_loss {
+            Some(best_loss) => loss < best_loss - self.min_delta,
+            None => true,
+        };
+        if improved {
+            self.best_loss = Some(loss);
+            self.wait = 0;
+            return;
+        }
+
+        self.wait += 1;
+        if self.wait > self.patience {
+            self.current_factor = (self.current_factor * self.gamma).max(self.min_factor);
+            self.wait = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_factor() {
+        let mut scheduler = ReduceOnPlateau::new(0.5, 1, 0.01, 0.2);
+
+        let mut factors = Vec::<f64>::new();
+        for (iteration, loss) in [1.0, 0.9, 0.895, 0.9, 0.8, 0.85, 0.81, 0.9, 0.9, 0.9, 0.9].iter().enumerate() {
+            factors.push(scheduler.factor(iteration as u32));
+            scheduler.observe_loss(*loss);
+        }
+        factors.push(scheduler.factor(11));
+
+        assert_eq!(factors, vec![1.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.25, 0.25, 0.2, 0.2, 0.2]);
+    }
+}
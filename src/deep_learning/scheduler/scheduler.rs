@@ -0,0 +1,15 @@
+// Scale learning rates of optimizers while learning.
+// A factor is multiplied to the initial learning rate of each optimizer,
+// so optimizers with different learning rates can share one scheduler.
+#[cfg_attr(test, mockall::automock)]
+pub trait LearningRateScheduler {
+    // Factor for the iteration (starting from 0)
+    fn factor(&mut self, iteration: u32) -> f64;
+    // True if the factor depends on the observed loss.
+    // The whole test data is evaluated every iteration only for such schedulers.
+    fn observes_loss(&self) -> bool {
+        false
+    }
+    // Observe loss of the whole test data after each iteration
+    fn observe_loss(&mut self, _loss: f64) {}
+}
@@ -0,0 +1,39 @@
+use crate::deep_learning::scheduler::*;
+
+// Multiply gamma every step_size iterations
+pub struct StepDecay {
+    step_size: u32,
+    gamma: f64,
+}
+impl StepDecay {
+    pub fn new(step_size: u32, gamma: f64) -> Self {
+        if step_size == 0 {
+            panic!("step_size must be greater than 0.");
+        }
+        Self {
+            step_size: step_size,
+            gamma: gamma,
+        }
+    }
+}
+impl LearningRateScheduler for StepDecay {
+    fn factor(&mut self, iteration: u32) -> f64 {
+        return self.gamma.powi((iteration / self.step_size) as i32);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::common::*;
+
+    #[test]
+    fn test_factor() {
+        let mut scheduler = StepDecay::new(3, 0.5);
+
+        let factors: Vec<f64> = (0..7).map(|i| round_digit(scheduler.factor(i), -6)).collect();
+
+        assert_eq!(factors, vec![1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.25].iter().map(|f| round_digit(*f, -6)).collect::<Vec<f64>>());
+    }
+}
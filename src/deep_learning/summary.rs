@@ -0,0 +1,141 @@
+use std::fmt;
+
+use crate::deep_learning::layer::*;
+
+pub struct LayerSummary {
+    pub layer_type: String,
+    pub layer_label: String,
+    pub output_shape: Vec<usize>,
+    pub parameter_num: usize,
+    pub trainable_parameter_num: usize,
+}
+impl LayerSummary {
+    pub fn new(layer_type: &str, layer_label: &str, output_shape: &[usize], (parameter_num, trainable_parameter_num): (usize, usize)) -> Self {
+        LayerSummary {
+            layer_type: layer_type.to_string(),
+            layer_label: layer_label.to_string(),
+            output_shape: output_shape.to_vec(),
+            parameter_num: parameter_num,
+            trainable_parameter_num: trainable_parameter_num,
+        }
+    }
+}
+
+// Layers are ordered from input to output
+pub struct NetworkSummary {
+    pub layers: Vec<LayerSummary>,
+}
+impl NetworkSummary {
+    pub fn total_parameter_num(&self) -> usize {
+        self.layers.iter().map(|l| l.parameter_num).sum()
+    }
+    pub fn trainable_parameter_num(&self) -> usize {
+        self.layers.iter().map(|l| l.trainable_parameter_num).sum()
+    }
+    pub fn parameter_memory_bytes(&self) -> usize {
+        self.total_parameter_num() * std::mem::size_of::<f64>()
+    }
+}
+impl fmt::Display for NetworkSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<20}{:<12}{:<20}{:>12}", "Layer (type)", "Label", "Output Shape", "Param #")?;
+        writeln!(f, "{}", "=".repeat(64))?;
+        for layer in &self.layers {
+            writeln!(
+                f,
+                "{:<20}{:<12}{:<20}{:>12}",
+                layer.layer_type,
+                layer.layer_label,
+                format!("{:?}", layer.output_shape),
+                layer.parameter_num
+            )?;
+        }
+        writeln!(f, "{}", "=".repeat(64))?;
+        writeln!(f, "Total params: {}", self.total_parameter_num())?;
+        writeln!(f, "Trainable params: {}", self.trainable_parameter_num())?;
+        writeln!(f, "Non-trainable params: {}", self.total_parameter_num() - self.trainable_parameter_num())?;
+        write!(f, "Parameter memory: {} bytes", self.parameter_memory_bytes())
+    }
+}
+
+// Output shape of the layer summarized last, which is the input shape of the next layer
+pub fn last_output_shape(summaries: &[LayerSummary]) -> Vec<usize> {
+    match summaries.last() {
+        Some(summary) => summary.output_shape.clone(),
+        None => panic!("No layer is summarized before"),
+    }
+}
+
+// Shape of the first parameter value below the layer
+pub fn parameter_shape(layer: &mut dyn NetworkLayer) -> Vec<usize> {
+    let mut shape = None;
+    layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+        if shape.is_none() {
+            shape = Some(parameter.get_value().shape().to_vec());
+        }
+    });
+    return shape.expect("No parameter");
+}
+
+// Count (all, trainable) parameter values below the layer
+pub fn count_parameters(layer: &mut dyn NetworkLayer) -> (usize, usize) {
+    let mut parameter_num = 0;
+    let mut trainable_parameter_num = 0;
+    layer.visit_parameters(&mut |_: &str, parameter: &mut dyn Parameter| {
+        parameter_num += parameter.get_value().len();
+        if parameter.is_trainable() {
+            trainable_parameter_num += parameter.get_value().len();
+        }
+    });
+    return (parameter_num, trainable_parameter_num);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::deep_learning::optimizer::*;
+
+    #[test]
+    fn test_count_parameters() {
+        let mut affine = Affine::new_random(
+            DirectValue::new_from_len(2, 3),
+            3,
+            4,
+            Sgd::new(0.01),
+            Sgd::new(0.01)
+        );
+        assert_eq!(count_parameters(&mut affine), (3 * 4 + 4, 3 * 4 + 4));
+
+        affine.set_trainable(false);
+        assert_eq!(count_parameters(&mut affine), (3 * 4 + 4, 0));
+    }
+
+    #[test]
+    fn test_network_summary() {
+        let summary = NetworkSummary {
+            layers: vec![
+                LayerSummary::new("DirectValue", "direct", &[2, 3], (0, 0)),
+                LayerSummary::new("Affine", "affine", &[2, 4], (16, 16)),
+                LayerSummary::new("SoftmaxWithLoss", "softmax", &[2, 1], (0, 0)),
+            ],
+        };
+        assert_eq!(summary.total_parameter_num(), 16);
+        assert_eq!(summary.trainable_parameter_num(), 16);
+        assert_eq!(summary.parameter_memory_bytes(), 16 * 8);
+
+        let lines: Vec<String> = format!("{}", summary).lines().map(|l| l.trim_end().to_string()).collect();
+        assert_eq!(lines, vec![
+            "Layer (type)        Label       Output Shape             Param #",
+            "================================================================",
+            "DirectValue         direct      [2, 3]                         0",
+            "Affine              affine      [2, 4]                        16",
+            "SoftmaxWithLoss     softmax     [2, 1]                         0",
+            "================================================================",
+            "Total params: 16",
+            "Trainable params: 16",
+            "Non-trainable params: 0",
+            "Parameter memory: 128 bytes",
+        ]);
+    }
+}
@@ -3,6 +3,10 @@ use deep_learning::image::*;
 use deep_learning::deep_learning::neural_network::*;
 use deep_learning::deep_learning::layer::*;
 use deep_learning::deep_learning::optimizer::*;
+use deep_learning::deep_learning::early_stopping::*;
+use deep_learning::deep_learning::callback::*;
+use deep_learning::deep_learning::metrics_logger::*;
+use deep_learning::deep_learning::hyperparameter_search::*;
 
 use ndarray::{
     Array2,
@@ -10,7 +14,7 @@ use ndarray::{
 };
 
 const TRN_IMG_SIZE: usize = 5000;
-const VAL_IMG_SIZE: usize = 1;
+const VAL_IMG_SIZE: usize = 1000;
 const TST_IMG_SIZE: usize = 2000;
 
 // Hyper parameter
@@ -34,11 +38,21 @@ fn main(){
 
 #[cfg (not (target_family = "wasm"))]
 fn main(){
-    lern_main();
+    // cargo run -- search
+    if std::env::args().nth(1).as_deref() == Some("search") {
+        search_main();
+    } else {
+        lern_main();
+    }
     // guess_main();
 }
 
 #[cfg (not (target_family = "wasm"))]
+// 1 for the pixels drawn, 0 for the others
+fn binarize(img: &Array2<f64>) -> Array2<f64> {
+    img.mapv(|pixel| if pixel > 0f64 {1f64} else {0f64})
+}
+
 fn lern_main() {
     // Load MNIST
     let mnist = MnistImages::new(TRN_IMG_SIZE, VAL_IMG_SIZE, TST_IMG_SIZE);
@@ -46,19 +60,17 @@ fn lern_main() {
     let trn_lbl = mnist.get_trn_lbl();
     let trn_lbl_onehot = mnist.get_trn_lbl_one_hot();
 
+    let val_img = mnist.get_val_img();
+    let val_lbl_onehot = mnist.get_val_lbl_one_hot();
+
     let tst_img = mnist.get_tst_img();
     let tst_lbl = mnist.get_tst_lbl();
     let tst_lbl_onehot = mnist.get_tst_lbl_one_hot();
 
     // Binarize
-    let mut trn_img_bi = Array2::<f64>::from_shape_fn(
-        trn_img.dim(),
-        |(i,j)| if trn_img[(i,j)] > 0f64 {1f64} else {0f64}
-    );
-    let mut tst_img_bi = Array2::<f64>::from_shape_fn(
-        tst_img.dim(),
-        |(i,j)| if tst_img[(i,j)] > 0f64 {1f64} else {0f64}
-    );
+    let mut trn_img_bi = binarize(trn_img);
+    let val_img_bi = binarize(val_img);
+    let mut tst_img_bi = binarize(tst_img);
 
     // Trim padding
     println!("Trimming images");
@@ -172,11 +184,30 @@ fn lern_main() {
     // );
     let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((MINIBATCH_SIZE, 10)));
     let mut nn = NeuralNetwork::new(layers);
+    println!("{}", nn.summary());
 
-    nn.learn(
+    let mut callbacks: Vec<Box<dyn TrainingCallback>> = vec![Box::new(PlotCallback::new())];
+    match MetricsLogger::new("metrics.csv", LogFormat::Csv) {
+        Ok(logger) => callbacks.push(Box::new(logger)),
+        Err(e) => println!("{}", e),
+    }
+
+    let history = nn.learn(
         LearningParameter{
             batch_size:     MINIBATCH_SIZE,
             iterations_num: ITERS_NUM,
+            sampling: MinibatchSampling::WithReplacement,
+            gradient_clipping: None,
+            scheduler: None,
+            seed: None,
+            checkpoint: None,
+            validation: Some(ValidationParameter {
+                interval: 100,
+                early_stopping: Some(EarlyStopping::new(5, 0.001)),
+                restore_best_weights: true,
+            }),
+            callbacks: callbacks,
+            threads_num: 1,
         }, 
         LearningResource {
             // trn_data:       trn_img.clone(),
@@ -185,8 +216,13 @@ fn lern_main() {
             // tst_data:       tst_img.clone(),
             tst_data:       tst_img_bi.clone(),
             tst_lbl_onehot: tst_lbl_onehot.clone(),
+            val_data:       val_img_bi.clone(),
+            val_lbl_onehot: val_lbl_onehot.clone(),
         }
     );
+    if let Some(best) = history.best_evaluation() {
+        println!("Best Validation Loss: {} (iteration:{})", best.val_loss, best.iteration);
+    }
 
     let res = nn.export();
     if let Err(e) = res {
@@ -206,7 +242,86 @@ fn guess_main() {
     let tst_img = mnist.get_tst_img();
     let tst_lbl_onehot = mnist.get_tst_lbl_one_hot();
 
-    nn.test(MINIBATCH_SIZE, &tst_img, &tst_lbl_onehot);
+    let result = nn.evaluate(MINIBATCH_SIZE, &tst_img, &tst_lbl_onehot);
+    println!("Test Loss: {}", result.loss);
+    println!("Test CorrectRate: {}% ({} samples)", result.correct_rate * 100.0, result.sample_num);
+    println!("{}", result.metrics);
+}
+
+#[cfg (not (target_family = "wasm"))]
+fn search_main() {
+    // Load MNIST
+    let mnist = MnistImages::new(TRN_IMG_SIZE, VAL_IMG_SIZE, TST_IMG_SIZE);
+    // Binarized like lern_main
+    let val_img_bi = binarize(mnist.get_val_img());
+    // Validation images only rank the trials. LearningParameter::validation is None, so val_data is empty.
+    let resource = LearningResource {
+        trn_data:       binarize(mnist.get_trn_img()),
+        trn_lbl_onehot: mnist.get_trn_lbl_one_hot().clone(),
+        tst_data:       binarize(mnist.get_tst_img()),
+        tst_lbl_onehot: mnist.get_tst_lbl_one_hot().clone(),
+        val_data:       Array2::<f64>::zeros((0, 28*28)),
+        val_lbl_onehot: Array2::<f64>::zeros((0, 10)),
+    };
+
+    let space = SearchSpace::new()
+        .add("learning_rate", ParameterSpace::LogUniform { min: 0.0001, max: 0.1 })
+        .add("dropout_rate", ParameterSpace::Values(vec![0.0, DROUPOUT_RATE, 0.3]))
+        .add("batch_size", ParameterSpace::Values(vec![50.0, MINIBATCH_SIZE as f64, 200.0]));
+
+    let result = random_search(
+        &space,
+        10,
+        0,
+        &resource,
+        &SelectionData {
+            data: &val_img_bi,
+            lbl_onehot: mnist.get_val_lbl_one_hot(),
+        },
+        |parameters| {
+            let batch_size = parameters.get("batch_size") as usize;
+            let learning_rate = parameters.get("learning_rate");
+            let layers = DirectValue::new(Array2::<f64>::zeros((batch_size, 28*28)));
+            let layers = Affine::new_random(
+                layers,
+                28*28,
+                100,
+                Adam::new(learning_rate, ADAM_FLICTION_M, ADAM_FLICTION_V),
+                Adam::new(learning_rate, ADAM_FLICTION_M, ADAM_FLICTION_V),
+            );
+            let layers = Relu::new(layers);
+            let layers = Dropout::new(layers, parameters.get("dropout_rate"));
+            let layers = Affine::new_random(
+                layers,
+                100,
+                10,
+                Adam::new(learning_rate, ADAM_FLICTION_M, ADAM_FLICTION_V),
+                Adam::new(learning_rate, ADAM_FLICTION_M, ADAM_FLICTION_V),
+            );
+            let layers = SoftmaxWithLoss::new(layers, Array2::<f64>::zeros((batch_size, 10)));
+            let mut nn = NeuralNetwork::new(layers);
+            nn.set_quiet(true);
+            nn
+        },
+        |parameters| LearningParameter {
+            batch_size:     parameters.get("batch_size") as usize,
+            iterations_num: 1000,
+            sampling: MinibatchSampling::WithReplacement,
+            gradient_clipping: None,
+            scheduler: None,
+            seed: Some(0),
+            checkpoint: None,
+            validation: None,
+            callbacks: Vec::new(),
+            threads_num: 1,
+        },
+    );
+    println!("{}", result);
+
+    let res = result.export_table("search.csv");
+    if let Err(e) = res {
+        println!("{}", e);
+    }
 }
 
 #[cfg(test)]